@@ -0,0 +1,311 @@
+//! Blocking I2C interface for display drivers
+
+use embedded_hal::i2c::{AddressMode, Error, I2c, Operation, SevenBitAddress, TenBitAddress};
+
+use display_interface::blocking::{ReadWriteDataCommand, WriteOnlyDataCommand};
+use display_interface::{DataFormat, DisplayError};
+
+use crate::{fill_chunk, Segment};
+
+/// Blocking I2C communication interface
+///
+/// This is the blocking counterpart of [`crate::I2CInterface`], sharing its configuration and the
+/// way commands and data are split into transactions.
+pub struct I2CInterface<I2C, const N: usize = 17, A = SevenBitAddress> {
+    i2c: I2C,
+    config: crate::I2CInterface<(), N, A>,
+}
+
+impl<I2C> I2CInterface<I2C> {
+    /// Create new I2C interface for communication with a display driver, see
+    /// [`crate::I2CInterface::new`]
+    pub fn new(i2c: I2C, addr: u8, data_byte: u8) -> Self {
+        Self {
+            i2c,
+            config: crate::I2CInterface::new((), addr, data_byte),
+        }
+    }
+}
+
+impl<I2C> I2CInterface<I2C, 17, TenBitAddress> {
+    /// Create new I2C interface for communication with a display driver with a 10-bit address
+    pub fn new_ten_bit(i2c: I2C, addr: TenBitAddress, data_byte: u8) -> Self {
+        Self {
+            i2c,
+            config: crate::I2CInterface::new_ten_bit((), addr, data_byte),
+        }
+    }
+}
+
+impl<I2C, const N: usize, A> I2CInterface<I2C, N, A>
+where
+    A: Copy,
+{
+    pub(crate) fn from_config(i2c: I2C, config: crate::I2CInterface<(), N, A>) -> Self {
+        Self { i2c, config }
+    }
+
+    /// Use a transfer buffer of `M` bytes, see [`crate::I2CInterface::with_buffer_size`]
+    pub fn with_buffer_size<const M: usize>(self) -> I2CInterface<I2C, M, A> {
+        I2CInterface {
+            i2c: self.i2c,
+            config: self.config.with_buffer_size::<M>(),
+        }
+    }
+
+    /// Set the control byte prefixed to commands, see [`crate::I2CInterface::with_command_byte`]
+    pub fn with_command_byte(mut self, byte: u8) -> Self {
+        self.config = self.config.with_command_byte(byte);
+        self
+    }
+
+    /// Set the maximum number of command bytes sent per I2C transaction, see
+    /// [`crate::I2CInterface::with_command_chunk_size`]
+    pub fn with_command_chunk_size(mut self, size: usize) -> Self {
+        self.config = self.config.with_command_chunk_size(size);
+        self
+    }
+
+    /// Send `U8` slices without copying them, see [`crate::I2CInterface::with_zero_copy`]
+    pub fn with_zero_copy(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_zero_copy(enabled);
+        self
+    }
+
+    /// Consume the display interface and return
+    /// the underlying peripherial driver
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C, const N: usize, A> I2CInterface<I2C, N, A>
+where
+    I2C: I2c<A>,
+    A: AddressMode + Copy,
+{
+    /// Send commands and data interleaved in a single I2C transaction, see
+    /// [`crate::I2CInterface::send_segments`]
+    pub fn send_segments(&mut self, segments: &[Segment<'_>]) -> Result<(), DisplayError> {
+        let mut writebuf = [0; N];
+        let (len, last) = match self.config.encode_segments(&mut writebuf, segments)? {
+            Some(encoded) => encoded,
+            // No-op if there's nothing to send
+            None => return Ok(()),
+        };
+
+        self.i2c
+            .transaction(
+                self.config.addr,
+                &mut [Operation::Write(&writebuf[..len]), Operation::Write(last)],
+            )
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+    }
+
+    /// Write the control byte followed by the bytes in a single transaction without copying them
+    fn write_prefixed(&mut self, control: u8, bytes: &[u8]) -> Result<(), DisplayError> {
+        // No-op if there's nothing to send
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        self.i2c
+            .transaction(
+                self.config.addr,
+                &mut [Operation::Write(&[control]), Operation::Write(bytes)],
+            )
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+    }
+
+    /// Write the bytes in as many transactions as needed to send at most `chunk_size` bytes at a
+    /// time, each prefixed with the given control byte
+    fn write_chunked(
+        &mut self,
+        control: u8,
+        chunk_size: usize,
+        mut bytes: impl Iterator<Item = u8>,
+    ) -> Result<(), DisplayError> {
+        let mut writebuf = [0; N];
+
+        while let Some(len) = fill_chunk(&mut writebuf, control, chunk_size, &mut bytes) {
+            self.i2c
+                .write(self.config.addr, &writebuf[..len])
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<I2C, const N: usize, A> WriteOnlyDataCommand for I2CInterface<I2C, N, A>
+where
+    I2C: I2c<A>,
+    A: AddressMode + Copy,
+{
+    fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        let (control, chunk_size) = (self.config.command_byte, self.config.command_chunk_size);
+        match cmds {
+            DataFormat::U8(slice) if self.config.zero_copy => self.write_prefixed(control, slice),
+            DataFormat::U8(slice) => self.write_chunked(control, chunk_size, slice.iter().copied()),
+            DataFormat::U8Iter(iter) => self.write_chunked(control, chunk_size, iter),
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        let control = self.config.data_byte;
+        match buf {
+            DataFormat::U8(slice) if self.config.zero_copy => self.write_prefixed(control, slice),
+            buf => {
+                let chunk_size = crate::I2CInterface::<(), N, A>::CHUNK_SIZE;
+                self.write_chunked(control, chunk_size, buf.into_bytes())
+            }
+        }
+    }
+}
+
+impl<I2C, const N: usize, A> ReadWriteDataCommand for I2CInterface<I2C, N, A>
+where
+    I2C: I2c<A>,
+    A: AddressMode + Copy,
+{
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        // Select data mode before reading back from the display
+        self.i2c
+            .write_read(self.config.addr, &[self.config.data_byte], buf)
+            .map_err(|e| DisplayError::BusReadError(e.kind().into()))
+    }
+
+    fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError> {
+        match cmd {
+            DataFormat::U8(slice) if self.config.zero_copy => self
+                .i2c
+                .transaction(
+                    self.config.addr,
+                    &mut [
+                        Operation::Write(&[self.config.command_byte]),
+                        Operation::Write(slice),
+                        Operation::Read(buf),
+                    ],
+                )
+                .map_err(|e| DisplayError::BusReadError(e.kind().into())),
+            DataFormat::U8(slice) => {
+                let mut writebuf = [0; N];
+                let len = self.config.encode_commands(&mut writebuf, slice)?;
+
+                self.i2c
+                    .write_read(self.config.addr, &writebuf[..len], buf)
+                    .map_err(|e| DisplayError::BusReadError(e.kind().into()))
+            }
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource, Operation};
+
+    use super::*;
+    use crate::COMMAND_BYTE;
+    use display_interface::BusErrorKind;
+
+    const ADDR: u8 = 0x3c;
+    const DATA_BYTE: u8 = 0x40;
+
+    /// I2C bus recording the bytes written in every transaction
+    #[derive(Default)]
+    struct MockI2c {
+        transactions: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl embedded_hal::i2c::ErrorType for MockI2c {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for MockI2c {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if address != ADDR {
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            }
+
+            let mut written = Vec::new();
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => written.extend_from_slice(bytes),
+                    Operation::Read(bytes) => bytes.fill(0),
+                }
+            }
+            self.transactions.push((address, written));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn commands_and_data_are_chunked() {
+        let cmds: Vec<u8> = (0..20).collect();
+        let mut data = [0x0102u16; 10];
+        let mut iface =
+            I2CInterface::new(MockI2c::default(), ADDR, DATA_BYTE).with_buffer_size::<9>();
+
+        iface.send_commands(DataFormat::U8(&cmds)).unwrap();
+        iface.send_data(DataFormat::U16LE(&mut data)).unwrap();
+
+        let mut expected = vec![
+            (ADDR, [&[COMMAND_BYTE][..], &cmds[0..8]].concat()),
+            (ADDR, [&[COMMAND_BYTE][..], &cmds[8..16]].concat()),
+            (ADDR, [&[COMMAND_BYTE][..], &cmds[16..20]].concat()),
+        ];
+        for len in [8, 8, 4].iter() {
+            let mut frame = vec![DATA_BYTE];
+            frame.extend([0x02, 0x01].iter().cycle().take(*len));
+            expected.push((ADDR, frame));
+        }
+        assert_eq!(iface.release().transactions, expected);
+    }
+
+    #[test]
+    fn zero_copy_and_reads_share_configuration() {
+        let data: Vec<u8> = (0..40).collect();
+        let mut status = [0xff; 2];
+        let mut iface = I2CInterface::new(MockI2c::default(), ADDR, DATA_BYTE)
+            .with_command_byte(0x80)
+            .with_zero_copy(true);
+
+        iface.send_data(DataFormat::U8(&data)).unwrap();
+        iface
+            .send_command_read(DataFormat::U8(&[0xaf]), &mut status)
+            .unwrap();
+
+        let mut frame = vec![DATA_BYTE];
+        frame.extend(0..40);
+        assert_eq!(
+            iface.release().transactions,
+            vec![(ADDR, frame), (ADDR, vec![0x80, 0xaf])]
+        );
+        assert_eq!(status, [0, 0]);
+    }
+
+    #[test]
+    fn bus_errors_are_classified() {
+        let mut iface = I2CInterface::new(MockI2c::default(), 0x3d, DATA_BYTE);
+
+        assert!(matches!(
+            iface.send_commands(DataFormat::U8(&[0xaf])),
+            Err(DisplayError::BusWriteError(BusErrorKind::NoAcknowledge))
+        ));
+    }
+}
@@ -0,0 +1,359 @@
+//! Configuration of I2C interfaces with presets for common display controllers
+
+use core::cell::RefCell;
+
+use display_interface::DisplayError;
+
+use crate::{blocking, I2CInterface, SharedI2CInterface, COMMAND_BYTE};
+
+/// Builder for [`I2CInterface`] and its blocking and shared variants
+///
+/// Start from a preset for the display controller or from [`new`](Self::new) and adjust the
+/// settings as needed, e.g. for a display with the address pin pulled high:
+///
+/// ```
+/// # use display_interface_i2c::I2CInterfaceBuilder;
+/// let builder = I2CInterfaceBuilder::ssd1306().address(0x3d);
+/// ```
+///
+/// `N` is the size of the transfer buffer of the built interface, see
+/// [`buffer_size`](Self::buffer_size).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct I2CInterfaceBuilder<const N: usize = 17> {
+    addr: u8,
+    command_byte: u8,
+    data_byte: u8,
+    command_chunk_size: Option<usize>,
+}
+
+impl I2CInterfaceBuilder {
+    /// Create new builder for a display at the given address, using the control bytes 0x00 for
+    /// commands and 0x40 for data and a transfer buffer of 17 bytes
+    pub fn new(addr: u8) -> Self {
+        Self {
+            addr,
+            command_byte: COMMAND_BYTE,
+            data_byte: 0x40,
+            command_chunk_size: None,
+        }
+    }
+
+    /// SSD1306 OLED controller at address 0x3C, sending a page of 128 columns per transaction
+    pub fn ssd1306() -> I2CInterfaceBuilder<129> {
+        Self::new(0x3c).buffer_size()
+    }
+
+    /// SH1106 OLED controller at address 0x3C, sending a page of 132 columns per transaction
+    ///
+    /// The SH1106 has no horizontal addressing mode, so drivers write the display page by page
+    /// including the 4 columns of RAM not shown on 128 pixel wide panels.
+    pub fn sh1106() -> I2CInterfaceBuilder<133> {
+        Self::new(0x3c).buffer_size()
+    }
+
+    /// SSD1309 OLED controller at address 0x3C, sending a page of 128 columns per transaction
+    pub fn ssd1309() -> I2CInterfaceBuilder<129> {
+        Self::new(0x3c).buffer_size()
+    }
+
+    /// SSD1327 grayscale OLED controller at address 0x3C, sending a row of 128 4-bit pixels per
+    /// transaction
+    pub fn ssd1327() -> I2CInterfaceBuilder<65> {
+        Self::new(0x3c).buffer_size()
+    }
+
+    /// IS31FL3731 LED matrix driver at address 0x74
+    ///
+    /// The IS31FL3731 takes the first byte of every write as the register address and increments
+    /// it for every following byte. Commands are written to the command register 0xFD selecting
+    /// the frame or function page, one byte per transaction. Data starts at the first PWM register
+    /// 0x24 of the selected page, so a whole frame of 144 PWM values has to be sent with a single
+    /// `send_data` call, which fits into one transaction.
+    ///
+    /// The registers before 0x24 can't be reached with this data byte, i.e. the LED control
+    /// registers 0x00 to 0x11 of a frame and the function registers of page 0x0B, among them the
+    /// shutdown register 0x0A that has to be set to bring the device up. Write them with further
+    /// interfaces on the same bus, see [`build_shared`](Self::build_shared), using the register
+    /// address as data byte:
+    ///
+    /// ```
+    /// # use core::cell::RefCell;
+    /// # use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+    /// # use display_interface_i2c::I2CInterfaceBuilder;
+    /// # async fn bring_up<I2C>(bus: &RefCell<I2C>) -> Result<(), DisplayError>
+    /// # where
+    /// #     I2C: embedded_hal_async::i2c::I2c,
+    /// # {
+    /// let mut shutdown = I2CInterfaceBuilder::is31fl3731().data_byte(0x0a).build_shared(bus)?;
+    /// let mut leds = I2CInterfaceBuilder::is31fl3731().data_byte(0x00).build_shared(bus)?;
+    ///
+    /// // Leave software shutdown on the function page, then enable all LEDs of frame 0
+    /// shutdown.send_command_with_data(DataFormat::U8(&[0x0b]), DataFormat::U8(&[0x01])).await?;
+    /// leds.send_command_with_data(DataFormat::U8(&[0x00]), DataFormat::U8(&[0xff; 18])).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is31fl3731() -> I2CInterfaceBuilder<145> {
+        Self {
+            command_byte: 0xfd,
+            data_byte: 0x24,
+            ..Self::new(0x74)
+        }
+        .command_chunk_size(1)
+        .buffer_size()
+    }
+}
+
+impl<const N: usize> I2CInterfaceBuilder<N> {
+    /// Use a transfer buffer of `M` bytes, including the leading control byte, see
+    /// [`I2CInterface::with_buffer_size`]
+    pub fn buffer_size<const M: usize>(self) -> I2CInterfaceBuilder<M> {
+        I2CInterfaceBuilder {
+            addr: self.addr,
+            command_byte: self.command_byte,
+            data_byte: self.data_byte,
+            command_chunk_size: self.command_chunk_size,
+        }
+    }
+
+    /// Set the 7-bit address of the display
+    pub fn address(mut self, addr: u8) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Set the control byte prefixed to commands
+    pub fn command_byte(mut self, byte: u8) -> Self {
+        self.command_byte = byte;
+        self
+    }
+
+    /// Set the control byte prefixed to data
+    pub fn data_byte(mut self, byte: u8) -> Self {
+        self.data_byte = byte;
+        self
+    }
+
+    /// Set the maximum number of command bytes sent per I2C transaction, see
+    /// [`I2CInterface::with_command_chunk_size`]
+    pub fn command_chunk_size(mut self, size: usize) -> Self {
+        self.command_chunk_size = Some(size);
+        self
+    }
+
+    /// Build the I2C interface
+    ///
+    /// Returns `InvalidAddressError` if the address isn't a 7-bit address or is one of the
+    /// addresses reserved by the I2C specification, 0x00 to 0x07 and 0x78 to 0x7F.
+    pub fn build<I2C>(self, i2c: I2C) -> Result<I2CInterface<I2C, N>, DisplayError> {
+        self.validate()?;
+
+        let iface = I2CInterface::new(i2c, self.addr, self.data_byte)
+            .with_buffer_size::<N>()
+            .with_command_byte(self.command_byte);
+        Ok(match self.command_chunk_size {
+            Some(size) => iface.with_command_chunk_size(size),
+            None => iface,
+        })
+    }
+
+    /// Build the I2C interface on a bus shared with other devices, see [`build`](Self::build)
+    pub fn build_shared<I2C>(
+        self,
+        bus: &RefCell<I2C>,
+    ) -> Result<SharedI2CInterface<'_, I2C, N>, DisplayError> {
+        Ok(SharedI2CInterface::from_config(bus, self.build(())?))
+    }
+
+    /// Build the blocking I2C interface, see [`build`](Self::build)
+    pub fn build_blocking<I2C>(
+        self,
+        i2c: I2C,
+    ) -> Result<blocking::I2CInterface<I2C, N>, DisplayError> {
+        Ok(blocking::I2CInterface::from_config(i2c, self.build(())?))
+    }
+
+    fn validate(&self) -> Result<(), DisplayError> {
+        match self.addr {
+            0x08..=0x77 => Ok(()),
+            _ => Err(DisplayError::InvalidAddressError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal_async as hal;
+    use futures::executor::block_on;
+    use hal::i2c::Operation;
+
+    use super::*;
+    use crate::mock::Ssd1306;
+    use crate::tests::MockI2c;
+    use display_interface::{DataFormat, WriteOnlyDataCommand};
+
+    #[test]
+    fn validates_address() {
+        for addr in [0x00, 0x07, 0x78, 0x80, 0xff].iter() {
+            assert!(matches!(
+                I2CInterfaceBuilder::new(*addr).build(()),
+                Err(DisplayError::InvalidAddressError)
+            ));
+        }
+        assert!(I2CInterfaceBuilder::new(0x08).build(()).is_ok());
+        assert!(I2CInterfaceBuilder::is31fl3731().build(()).is_ok());
+    }
+
+    #[test]
+    fn presets_set_buffer_size() {
+        let data = [0xff; 140];
+        let mut iface = I2CInterfaceBuilder::sh1106()
+            .build(MockI2c::default())
+            .unwrap();
+
+        block_on(iface.send_data(DataFormat::U8(&data))).unwrap();
+
+        let lens: Vec<_> = iface
+            .release()
+            .transactions
+            .iter()
+            .map(|(_, bytes)| bytes.len())
+            .collect();
+        assert_eq!(lens, [133, 9]);
+    }
+
+    #[test]
+    fn is31fl3731_sends_frame_in_one_transaction() {
+        let frame: Vec<u8> = (0..144).collect();
+        let mut iface = I2CInterfaceBuilder::is31fl3731()
+            .build(MockI2c::default())
+            .unwrap();
+
+        block_on(iface.send_commands(DataFormat::U8(&[0x0b, 0x00]))).unwrap();
+        block_on(iface.send_data(DataFormat::U8(&frame))).unwrap();
+
+        let mut pwm = vec![0x24];
+        pwm.extend(&frame);
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                (0x74, vec![0xfd, 0x0b]),
+                (0x74, vec![0xfd, 0x00]),
+                (0x74, pwm)
+            ]
+        );
+    }
+
+    /// IS31FL3731 with the 8 frame pages and the function page, writing the bytes of every
+    /// transaction to the registers starting at the address in the first byte
+    struct Is31fl3731 {
+        page: u8,
+        frames: [[u8; 0xb4]; 8],
+        function: [u8; 0x0d],
+    }
+
+    impl Is31fl3731 {
+        fn new() -> Self {
+            Self {
+                page: 0,
+                frames: [[0; 0xb4]; 8],
+                function: [0; 0x0d],
+            }
+        }
+
+        /// Brightness of an LED of the displayed frame, 0 while the device is shut down or the LED
+        /// is disabled
+        fn brightness(&self, led: usize) -> u8 {
+            let frame = &self.frames[usize::from(self.function[0x01] & 0x07)];
+            let enabled = frame[led / 8] & 1 << (led % 8) != 0;
+            if self.function[0x0a] & 0x01 != 0 && enabled {
+                frame[0x24 + led]
+            } else {
+                0
+            }
+        }
+    }
+
+    impl hal::i2c::ErrorType for Is31fl3731 {
+        type Error = hal::i2c::ErrorKind;
+    }
+
+    impl hal::i2c::I2c<u8> for Is31fl3731 {
+        async fn transaction(
+            &mut self,
+            _address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            let mut written = Vec::new();
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => written.extend_from_slice(bytes),
+                    Operation::Read(_) => return Err(hal::i2c::ErrorKind::Other),
+                }
+            }
+
+            let (&reg, bytes) = written.split_first().ok_or(hal::i2c::ErrorKind::Other)?;
+            let registers: &mut [u8] = match (reg, self.page) {
+                (0xfd, _) => {
+                    self.page = bytes[0];
+                    return Ok(());
+                }
+                (_, 0x0b) => &mut self.function,
+                (_, page) => &mut self.frames[usize::from(page)],
+            };
+            for (register, &byte) in registers[usize::from(reg)..].iter_mut().zip(bytes) {
+                *register = byte;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn is31fl3731_brings_up_device() {
+        let bus = RefCell::new(Is31fl3731::new());
+        let preset = I2CInterfaceBuilder::is31fl3731();
+        let mut shutdown = preset.data_byte(0x0a).build_shared(&bus).unwrap();
+        let mut leds = preset.data_byte(0x00).build_shared(&bus).unwrap();
+        let mut pwm = preset.build_shared(&bus).unwrap();
+        let frame: Vec<u8> = (1..=144).collect();
+
+        block_on(async {
+            pwm.send_command_with_data(DataFormat::U8(&[0x00]), DataFormat::U8(&frame))
+                .await?;
+            // Still shut down and all LEDs disabled
+            assert_eq!(bus.borrow().brightness(0), 0);
+
+            shutdown
+                .send_command_with_data(DataFormat::U8(&[0x0b]), DataFormat::U8(&[0x01]))
+                .await?;
+            leds.send_command_with_data(DataFormat::U8(&[0x00]), DataFormat::U8(&[0xff; 18]))
+                .await
+        })
+        .unwrap();
+
+        let display = bus.borrow();
+        assert_eq!(display.function[0x0a], 0x01);
+        assert!((0..144).all(|led| display.brightness(led) == frame[led]));
+    }
+
+    #[test]
+    fn preset_drives_display() {
+        let mut display = Ssd1306::new(0x3d);
+        let mut iface = I2CInterfaceBuilder::ssd1306()
+            .address(0x3d)
+            .command_chunk_size(1)
+            .build(&mut display)
+            .unwrap();
+
+        block_on(iface.send_commands(DataFormat::U8(&[0xb1, 0xaf]))).unwrap();
+        block_on(iface.send_data(DataFormat::U8(&[0x01]))).unwrap();
+
+        assert!(display.is_display_on());
+        assert!(display.pixel(0, 8));
+    }
+}
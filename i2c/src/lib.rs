@@ -1,125 +1,738 @@
 #![no_std]
-#![feature(type_alias_impl_trait)]
 
 //! Generic I2C interface for display drivers
-use core::future::Future;
-
 use embedded_hal_async as hal;
+use hal::i2c::{AddressMode, Error, Operation, SevenBitAddress, TenBitAddress};
+
+use display_interface::{DataFormat, DisplayError, ReadWriteDataCommand, WriteOnlyDataCommand};
+
+pub mod blocking;
+mod builder;
+#[cfg(any(feature = "mock", test))]
+pub mod mock;
+mod shared;
+
+pub use builder::I2CInterfaceBuilder;
+pub use shared::SharedI2CInterface;
+
+/// Control byte prefixed to command transactions
+const COMMAND_BYTE: u8 = 0x00;
+
+/// Continuation bit of the control byte, only a single byte follows before the next control byte
+const CONTROL_CO: u8 = 0x80;
 
-use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+/// Fill the transfer buffer with the control byte followed by at most `chunk_size` bytes and
+/// return the number of bytes to write, or `None` if there are no bytes left to send
+fn fill_chunk<const N: usize>(
+    writebuf: &mut [u8; N],
+    control: u8,
+    chunk_size: usize,
+    bytes: &mut impl Iterator<Item = u8>,
+) -> Option<usize> {
+    writebuf[0] = control;
+    let len = 1 + writebuf[1..=chunk_size]
+        .iter_mut()
+        .zip(bytes)
+        .map(|(slot, byte)| *slot = byte)
+        .count();
+
+    (len > 1).then_some(len)
+}
+
+/// Part of a transaction sent with [`I2CInterface::send_segments`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Command bytes
+    Commands(&'a [u8]),
+    /// Data bytes
+    Data(&'a [u8]),
+}
 
 /// I2C communication interface
-pub struct I2CInterface<I2C> {
+///
+/// `N` is the size of the transfer buffer, including the leading control byte, so at most `N - 1`
+/// bytes of data are sent per I2C transaction. The buffer lives on the stack for the duration of
+/// each call, e.g. `I2CInterface::new(i2c, 0x3c, 0x40).with_buffer_size::<1025>()` writes a 128x64
+/// SSD1306 framebuffer in a single transaction.
+///
+/// The interface owns the bus, but can borrow it as well since `&mut I2C` implements the I2C
+/// traits, e.g. `I2CInterface::new(&mut i2c, 0x3c, 0x40)`. See [`SharedI2CInterface`] to share the
+/// bus with other devices for the lifetime of the interface.
+///
+/// `A` is the address mode of the bus, 7-bit addresses by default; use
+/// [`new_ten_bit`](I2CInterface::new_ten_bit) for displays with a 10-bit address.
+pub struct I2CInterface<I2C, const N: usize = 17, A = SevenBitAddress> {
     i2c: I2C,
-    addr: u8,
+    addr: A,
+    command_byte: u8,
     data_byte: u8,
+    command_chunk_size: usize,
+    zero_copy: bool,
 }
 
-impl<I2C> I2CInterface<I2C>
-where
-    I2C: hal::i2c::I2c<u8>,
-{
+impl<I2C> I2CInterface<I2C> {
     /// Create new I2C interface for communication with a display driver
+    ///
+    /// The interface uses a transfer buffer of 17 bytes, see
+    /// [`with_buffer_size`](Self::with_buffer_size) to change it.
     pub fn new(i2c: I2C, addr: u8, data_byte: u8) -> Self {
         Self {
             i2c,
             addr,
+            command_byte: COMMAND_BYTE,
+            data_byte,
+            command_chunk_size: Self::CHUNK_SIZE,
+            zero_copy: false,
+        }
+    }
+}
+
+impl<I2C> I2CInterface<I2C, 17, TenBitAddress> {
+    /// Create new I2C interface for communication with a display driver with a 10-bit address
+    ///
+    /// See [`new`](I2CInterface::new) for the 7-bit version.
+    pub fn new_ten_bit(i2c: I2C, addr: TenBitAddress, data_byte: u8) -> Self {
+        Self {
+            i2c,
+            addr,
+            command_byte: COMMAND_BYTE,
             data_byte,
+            command_chunk_size: Self::CHUNK_SIZE,
+            zero_copy: false,
+        }
+    }
+}
+
+impl<I2C, const N: usize, A> I2CInterface<I2C, N, A>
+where
+    A: Copy,
+{
+    /// Number of payload bytes fitting into the transfer buffer after the control byte
+    const CHUNK_SIZE: usize = {
+        assert!(
+            N > 1,
+            "transfer buffer must fit the control byte and at least one byte"
+        );
+        N - 1
+    };
+
+    /// Use a transfer buffer of `M` bytes, including the leading control byte
+    ///
+    /// This resets the command chunk size to the largest one fitting into the new buffer.
+    pub fn with_buffer_size<const M: usize>(self) -> I2CInterface<I2C, M, A> {
+        I2CInterface {
+            i2c: self.i2c,
+            addr: self.addr,
+            command_byte: self.command_byte,
+            data_byte: self.data_byte,
+            command_chunk_size: I2CInterface::<I2C, M, A>::CHUNK_SIZE,
+            zero_copy: self.zero_copy,
         }
     }
 
+    /// Set the control byte prefixed to commands, 0x00 by default
+    pub fn with_command_byte(mut self, byte: u8) -> Self {
+        self.command_byte = byte;
+        self
+    }
+
+    /// Set the maximum number of command bytes sent per I2C transaction, longer command batches
+    /// are split into several transactions
+    ///
+    /// The size is clamped to `1..=N - 1`.
+    pub fn with_command_chunk_size(mut self, size: usize) -> Self {
+        self.command_chunk_size = size.clamp(1, Self::CHUNK_SIZE);
+        self
+    }
+
+    /// Send `U8` slices without copying them into the transfer buffer
+    ///
+    /// When enabled, the control byte and the whole caller's slice are sent as two write
+    /// operations of a single I2C transaction instead of being split into chunks. This relies on
+    /// the HAL implementing `I2c::transaction` and sending adjacent write operations without a
+    /// repeated start condition in between.
+    pub fn with_zero_copy(mut self, enabled: bool) -> Self {
+        self.zero_copy = enabled;
+        self
+    }
+
     /// Consume the display interface and return
     /// the underlying peripherial driver
     pub fn release(self) -> I2C {
         self.i2c
     }
+
+    /// Interface with the same configuration communicating over another bus handle
+    fn attach<J>(&self, i2c: J) -> I2CInterface<J, N, A> {
+        I2CInterface {
+            i2c,
+            addr: self.addr,
+            command_byte: self.command_byte,
+            data_byte: self.data_byte,
+            command_chunk_size: self.command_chunk_size,
+            zero_copy: self.zero_copy,
+        }
+    }
+
+    /// Encode all but the last segment with the continuation bit set, followed by the control
+    /// byte of the last segment, into the transfer buffer, see
+    /// [`send_segments`](Self::send_segments)
+    ///
+    /// Returns the number of encoded bytes and the bytes of the last segment, `None` if there's
+    /// nothing to send.
+    fn encode_segments<'s>(
+        &self,
+        writebuf: &mut [u8; N],
+        segments: &[Segment<'s>],
+    ) -> Result<Option<(usize, &'s [u8])>, DisplayError> {
+        let mut segments = segments.iter().filter_map(|segment| match *segment {
+            Segment::Commands(bytes) if !bytes.is_empty() => Some((self.command_byte, bytes)),
+            Segment::Data(bytes) if !bytes.is_empty() => Some((self.data_byte, bytes)),
+            _ => None,
+        });
+
+        // Nothing to send
+        let mut current = match segments.next() {
+            Some(segment) => segment,
+            None => return Ok(None),
+        };
+
+        let mut i = 0;
+        let mut push = |byte| {
+            *writebuf
+                .get_mut(i)
+                .ok_or(DisplayError::BufferOverflowError)? = byte;
+            i += 1;
+            Ok::<_, DisplayError>(())
+        };
+
+        for next in segments {
+            let (control, bytes) = current;
+            for &byte in bytes {
+                push(control | CONTROL_CO)?;
+                push(byte)?;
+            }
+            current = next;
+        }
+
+        // The last segment is written from its slice, only its control byte goes into the buffer
+        let (control, bytes) = current;
+        push(control)?;
+
+        Ok(Some((i, bytes)))
+    }
+
+    /// Copy the control byte and the commands sent ahead of a read into the transfer buffer and
+    /// return the number of bytes to write
+    fn encode_commands(&self, writebuf: &mut [u8; N], cmds: &[u8]) -> Result<usize, DisplayError> {
+        // The commands have to go out in a single transaction ahead of the read, so they can't
+        // be chunked; refuse batches which don't fit
+        writebuf[0] = self.command_byte;
+        writebuf
+            .get_mut(1..=cmds.len())
+            .ok_or(DisplayError::InvalidFormatError)?
+            .copy_from_slice(cmds);
+
+        Ok(cmds.len() + 1)
+    }
 }
 
-impl<I2C> WriteOnlyDataCommand for I2CInterface<I2C>
+impl<I2C, const N: usize, A> I2CInterface<I2C, N, A>
 where
-    I2C: hal::i2c::I2c<u8>,
+    I2C: hal::i2c::I2c<A>,
+    A: AddressMode + Copy,
 {
-    type SendCommandsFuture<'a> = impl Future<Output = Result<(), DisplayError>> + 'a where Self: 'a;
-    type SendDataFuture<'a> = impl Future<Output = Result<(), DisplayError>> + 'a where Self: 'a;
-
-    fn send_commands<'a>(&'a mut self, cmds: DataFormat<'a>) -> Self::SendCommandsFuture<'a> {
-        async move {
-            // Copy over given commands to new aray to prefix with command identifier
-            match cmds {
-                DataFormat::U8(slice) => {
-                    let mut writebuf: [u8; 8] = [0; 8];
-                    writebuf[1..=slice.len()].copy_from_slice(&slice[0..slice.len()]);
-
-                    self.i2c
-                        .write(self.addr, &writebuf[..=slice.len()])
-                        .await
-                        .map_err(|_| DisplayError::BusWriteError)
-                }
-                _ => Err(DisplayError::DataFormatNotImplemented),
+    /// Send commands and data interleaved in a single I2C transaction
+    ///
+    /// Every byte of all but the last non-empty segment is preceded by its control byte with the
+    /// continuation bit (0x80) set, as supported by SSD1306-family controllers, while the last
+    /// segment is streamed after a single control byte. This saves the start condition and the
+    /// address of separate transactions, e.g. when setting the address window before a partial
+    /// redraw.
+    ///
+    /// The encoded segments and the control byte of the last segment are copied into the transfer
+    /// buffer, while the last segment is written without copying it, so it isn't limited in size.
+    /// Returns `BufferOverflowError` if the encoded part doesn't fit into the transfer buffer of
+    /// `N` bytes.
+    pub async fn send_segments(&mut self, segments: &[Segment<'_>]) -> Result<(), DisplayError> {
+        let mut writebuf = [0; N];
+        let (len, last) = match self.encode_segments(&mut writebuf, segments)? {
+            Some(encoded) => encoded,
+            // No-op if there's nothing to send
+            None => return Ok(()),
+        };
+
+        self.i2c
+            .transaction(
+                self.addr,
+                &mut [Operation::Write(&writebuf[..len]), Operation::Write(last)],
+            )
+            .await
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+    }
+
+    /// Write the control byte followed by the bytes in a single transaction without copying them
+    async fn write_prefixed(&mut self, control: u8, bytes: &[u8]) -> Result<(), DisplayError> {
+        // No-op if there's nothing to send
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        self.i2c
+            .transaction(
+                self.addr,
+                &mut [Operation::Write(&[control]), Operation::Write(bytes)],
+            )
+            .await
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+    }
+
+    /// Write the bytes in as many transactions as needed to send at most `chunk_size` bytes at a
+    /// time, each prefixed with the given control byte
+    async fn write_chunked(
+        &mut self,
+        control: u8,
+        chunk_size: usize,
+        mut bytes: impl Iterator<Item = u8>,
+    ) -> Result<(), DisplayError> {
+        let mut writebuf = [0; N];
+
+        while let Some(len) = fill_chunk(&mut writebuf, control, chunk_size, &mut bytes) {
+            self.i2c
+                .write(self.addr, &writebuf[..len])
+                .await
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<I2C, const N: usize, A> WriteOnlyDataCommand for I2CInterface<I2C, N, A>
+where
+    I2C: hal::i2c::I2c<A>,
+    A: AddressMode + Copy,
+{
+    async fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        match cmds {
+            DataFormat::U8(slice) if self.zero_copy => {
+                self.write_prefixed(self.command_byte, slice).await
+            }
+            DataFormat::U8(slice) => {
+                self.write_chunked(
+                    self.command_byte,
+                    self.command_chunk_size,
+                    slice.iter().copied(),
+                )
+                .await
             }
+            DataFormat::U8Iter(iter) => {
+                self.write_chunked(self.command_byte, self.command_chunk_size, iter)
+                    .await
+            }
+            _ => Err(DisplayError::DataFormatNotImplemented),
         }
     }
 
-    fn send_data<'a>(&'a mut self, buf: DataFormat<'a>) -> Self::SendDataFuture<'a> {
-        async move {
-            match buf {
-                DataFormat::U8(slice) => {
-                    // No-op if the data buffer is empty
-                    if slice.is_empty() {
-                        return Ok(());
-                    }
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        match buf {
+            DataFormat::U8(slice) if self.zero_copy => {
+                self.write_prefixed(self.data_byte, slice).await
+            }
+            buf => {
+                self.write_chunked(self.data_byte, Self::CHUNK_SIZE, buf.into_bytes())
+                    .await
+            }
+        }
+    }
+}
 
-                    let mut writebuf = [0; 17];
+impl<I2C, const N: usize, A> ReadWriteDataCommand for I2CInterface<I2C, N, A>
+where
+    I2C: hal::i2c::I2c<A>,
+    A: AddressMode + Copy,
+{
+    async fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        // Select data mode before reading back from the display
+        self.i2c
+            .write_read(self.addr, &[self.data_byte], buf)
+            .await
+            .map_err(|e| DisplayError::BusReadError(e.kind().into()))
+    }
 
-                    // Data mode
-                    writebuf[0] = self.data_byte;
+    async fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError> {
+        match cmd {
+            DataFormat::U8(slice) if self.zero_copy => self
+                .i2c
+                .transaction(
+                    self.addr,
+                    &mut [
+                        Operation::Write(&[self.command_byte]),
+                        Operation::Write(slice),
+                        Operation::Read(buf),
+                    ],
+                )
+                .await
+                .map_err(|e| DisplayError::BusReadError(e.kind().into())),
+            DataFormat::U8(slice) => {
+                let mut writebuf = [0; N];
+                let len = self.encode_commands(&mut writebuf, slice)?;
 
-                    for c in slice.chunks(16) {
-                        let chunk_len = c.len();
+                self.i2c
+                    .write_read(self.addr, &writebuf[..len], buf)
+                    .await
+                    .map_err(|e| DisplayError::BusReadError(e.kind().into()))
+            }
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+}
 
-                        // Copy over all data from buffer, leaving the data command byte intact
-                        writebuf[1..=chunk_len].copy_from_slice(c);
+#[cfg(test)]
+mod tests {
+    extern crate std;
 
-                        self.i2c
-                            .write(self.addr, &writebuf[0..=chunk_len])
-                            .await
-                            .map_err(|_| DisplayError::BusWriteError)?;
-                    }
+    use std::vec;
+    use std::vec::Vec;
 
-                    Ok(())
-                }
-                DataFormat::U8Iter(iter) => {
-                    let mut writebuf = [0; 17];
-                    let mut i = 1;
-                    let len = writebuf.len();
-
-                    // Data mode
-                    writebuf[0] = self.data_byte;
-
-                    for byte in iter.into_iter() {
-                        writebuf[i] = byte;
-                        i += 1;
-
-                        if i == len {
-                            self.i2c
-                                .write(self.addr, &writebuf[0..=len])
-                                .await
-                                .map_err(|_| DisplayError::BusWriteError)?;
-                            i = 1;
-                        }
-                    }
-
-                    if i > 1 {
-                        self.i2c
-                            .write(self.addr, &writebuf[0..=i])
-                            .await
-                            .map_err(|_| DisplayError::BusWriteError)?;
-                    }
-
-                    Ok(())
+    use futures::executor::block_on;
+
+    use super::*;
+    use display_interface::BusErrorKind;
+
+    const ADDR: u8 = 0x3c;
+    const DATA_BYTE: u8 = 0x40;
+
+    /// I2C bus recording the bytes written in every transaction
+    #[derive(Default)]
+    pub(crate) struct MockI2c {
+        pub(crate) transactions: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl hal::i2c::ErrorType for MockI2c {
+        type Error = hal::i2c::ErrorKind;
+    }
+
+    impl hal::i2c::I2c<u8> for MockI2c {
+        async fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            let mut written = Vec::new();
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => written.extend_from_slice(bytes),
+                    Operation::Read(bytes) => bytes.fill(0),
                 }
-                _ => Err(DisplayError::DataFormatNotImplemented),
             }
+            self.transactions.push((address, written));
+            Ok(())
         }
     }
+
+    fn frame(control: u8, bytes: impl IntoIterator<Item = u8>) -> (u8, Vec<u8>) {
+        let mut frame = vec![control];
+        frame.extend(bytes);
+        (ADDR, frame)
+    }
+
+    fn interface() -> I2CInterface<MockI2c> {
+        I2CInterface::new(MockI2c::default(), ADDR, DATA_BYTE)
+    }
+
+    #[test]
+    fn u8_slice_is_chunked() {
+        let data: Vec<u8> = (0..40).collect();
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U8(&data))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(DATA_BYTE, 0..16),
+                frame(DATA_BYTE, 16..32),
+                frame(DATA_BYTE, 32..40),
+            ]
+        );
+    }
+
+    #[test]
+    fn u8_iter_flushes_full_chunks() {
+        let mut iter = 0..32;
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U8Iter(&mut iter))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![frame(DATA_BYTE, 0..16), frame(DATA_BYTE, 16..32)]
+        );
+    }
+
+    #[test]
+    fn u8_iter_sends_remainder() {
+        let mut iter = 0..19;
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U8Iter(&mut iter))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![frame(DATA_BYTE, 0..16), frame(DATA_BYTE, 16..19)]
+        );
+    }
+
+    #[test]
+    fn empty_data_is_noop() {
+        let mut iter = core::iter::empty();
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U8(&[]))).unwrap();
+        block_on(iface.send_data(DataFormat::U8Iter(&mut iter))).unwrap();
+
+        assert_eq!(iface.release().transactions, vec![]);
+    }
+
+    #[test]
+    fn u16_data_is_serialized_in_requested_byte_order() {
+        let mut be = [0x0102, 0x0304];
+        let mut le = [0x0102, 0x0304];
+        let mut be_iter = core::iter::once(0x0506);
+        let mut le_iter = core::iter::once(0x0506);
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U16BE(&mut be))).unwrap();
+        block_on(iface.send_data(DataFormat::U16LE(&mut le))).unwrap();
+        block_on(iface.send_data(DataFormat::U16BEIter(&mut be_iter))).unwrap();
+        block_on(iface.send_data(DataFormat::U16LEIter(&mut le_iter))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(DATA_BYTE, [0x01, 0x02, 0x03, 0x04]),
+                frame(DATA_BYTE, [0x02, 0x01, 0x04, 0x03]),
+                frame(DATA_BYTE, [0x05, 0x06]),
+                frame(DATA_BYTE, [0x06, 0x05]),
+            ]
+        );
+    }
+
+    #[test]
+    fn u32_data_is_serialized_in_requested_byte_order() {
+        let mut be = [0x0102_0304];
+        let mut le = [0x0102_0304];
+        let mut be_iter = core::iter::once(0x0506_0708);
+        let mut le_iter = core::iter::once(0x0506_0708);
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U32BE(&mut be))).unwrap();
+        block_on(iface.send_data(DataFormat::U32LE(&mut le))).unwrap();
+        block_on(iface.send_data(DataFormat::U32BEIter(&mut be_iter))).unwrap();
+        block_on(iface.send_data(DataFormat::U32LEIter(&mut le_iter))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(DATA_BYTE, [0x01, 0x02, 0x03, 0x04]),
+                frame(DATA_BYTE, [0x04, 0x03, 0x02, 0x01]),
+                frame(DATA_BYTE, [0x05, 0x06, 0x07, 0x08]),
+                frame(DATA_BYTE, [0x08, 0x07, 0x06, 0x05]),
+            ]
+        );
+    }
+
+    #[test]
+    fn pixel_data_is_sent_as_three_bytes() {
+        let mut rgb888 = core::iter::once([0x04, 0x05, 0x06]);
+        let mut rgb666 = core::iter::once(0x3f << 12 | 0x01);
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U24(&[[0x01, 0x02, 0x03]]))).unwrap();
+        block_on(iface.send_data(DataFormat::U24Iter(&mut rgb888))).unwrap();
+        block_on(iface.send_data(DataFormat::RGB666(&[0x20 << 6]))).unwrap();
+        block_on(iface.send_data(DataFormat::RGB666Iter(&mut rgb666))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(DATA_BYTE, [0x01, 0x02, 0x03]),
+                frame(DATA_BYTE, [0x04, 0x05, 0x06]),
+                frame(DATA_BYTE, [0x00, 0x80, 0x00]),
+                frame(DATA_BYTE, [0xfc, 0x00, 0x04]),
+            ]
+        );
+    }
+
+    #[test]
+    fn immutable_u16_data_is_serialized_in_requested_byte_order() {
+        static PIXELS: [u16; 2] = [0x0102, 0x0304];
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U16BERef(&PIXELS))).unwrap();
+        block_on(iface.send_data(DataFormat::U16LERef(&PIXELS))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(DATA_BYTE, [0x01, 0x02, 0x03, 0x04]),
+                frame(DATA_BYTE, [0x02, 0x01, 0x04, 0x03]),
+            ]
+        );
+    }
+
+    #[test]
+    fn u16_data_is_chunked_on_byte_boundaries() {
+        let mut data: Vec<u16> = (0..10).collect();
+        let mut iface =
+            I2CInterface::new(MockI2c::default(), ADDR, DATA_BYTE).with_buffer_size::<4>();
+
+        block_on(iface.send_data(DataFormat::U16BE(&mut data))).unwrap();
+
+        let bytes: Vec<u8> = (0..10u16).flat_map(u16::to_be_bytes).collect();
+        let expected: Vec<_> = bytes
+            .chunks(3)
+            .map(|c| frame(DATA_BYTE, c.iter().copied()))
+            .collect();
+        assert_eq!(iface.release().transactions, expected);
+    }
+
+    #[test]
+    fn buffer_size_sets_chunk_size() {
+        let data: Vec<u8> = (0..10).collect();
+        let mut iface =
+            I2CInterface::new(MockI2c::default(), ADDR, DATA_BYTE).with_buffer_size::<5>();
+
+        block_on(iface.send_data(DataFormat::U8(&data))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(DATA_BYTE, 0..4),
+                frame(DATA_BYTE, 4..8),
+                frame(DATA_BYTE, 8..10),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_copy_sends_single_transaction() {
+        let data: Vec<u8> = (0..40).collect();
+        let cmds: Vec<u8> = (0..20).collect();
+        let mut iface = interface().with_zero_copy(true);
+
+        block_on(iface.send_commands(DataFormat::U8(&cmds))).unwrap();
+        block_on(iface.send_data(DataFormat::U8(&data))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![frame(COMMAND_BYTE, 0..20), frame(DATA_BYTE, 0..40)]
+        );
+    }
+
+    #[test]
+    fn long_command_batch_is_chunked() {
+        let cmds: Vec<u8> = (0..20).collect();
+        let mut iface = interface().with_command_chunk_size(8);
+
+        block_on(iface.send_commands(DataFormat::U8(&cmds))).unwrap();
+
+        assert_eq!(
+            iface.release().transactions,
+            vec![
+                frame(COMMAND_BYTE, 0..8),
+                frame(COMMAND_BYTE, 8..16),
+                frame(COMMAND_BYTE, 16..20),
+            ]
+        );
+    }
+
+    #[test]
+    fn ten_bit_address_is_used() {
+        /// I2C bus with 10-bit addresses recording the address of every transaction
+        #[derive(Default)]
+        struct TenBitI2c {
+            addresses: Vec<u16>,
+        }
+
+        impl hal::i2c::ErrorType for TenBitI2c {
+            type Error = hal::i2c::ErrorKind;
+        }
+
+        impl hal::i2c::I2c<TenBitAddress> for TenBitI2c {
+            async fn transaction(
+                &mut self,
+                address: u16,
+                _operations: &mut [Operation<'_>],
+            ) -> Result<(), Self::Error> {
+                self.addresses.push(address);
+                Ok(())
+            }
+        }
+
+        let mut iface = I2CInterface::new_ten_bit(TenBitI2c::default(), 0x2a5, DATA_BYTE);
+
+        block_on(iface.send_commands(DataFormat::U8(&[0xaf]))).unwrap();
+        block_on(iface.send_data(DataFormat::U8(&[0xff]))).unwrap();
+
+        assert_eq!(iface.release().addresses, vec![0x2a5, 0x2a5]);
+    }
+
+    #[test]
+    fn segments_are_sent_in_single_transaction() {
+        let mut display = mock::Ssd1306::new(ADDR);
+        let mut iface = I2CInterface::new(&mut display, ADDR, DATA_BYTE).with_buffer_size::<12>();
+
+        block_on(iface.send_segments(&[
+            Segment::Commands(&[0xb3, 0x02]),
+            Segment::Data(&[]),
+            Segment::Data(&[0xaa, 0x55]),
+        ]))
+        .unwrap();
+
+        // Only the encoded commands and the control byte of the data are limited by the buffer
+        let data = [0x0f; 32];
+        block_on(
+            iface.send_segments(&[Segment::Commands(&[0xb5, 0x10, 0x00]), Segment::Data(&data)]),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            block_on(
+                iface.send_segments(&[Segment::Commands(&[0xb0; 6]), Segment::Data(&[0xff]),])
+            ),
+            Err(DisplayError::BufferOverflowError)
+        ));
+
+        assert_eq!(display.ram()[3 * mock::WIDTH + 2..][..2], [0xaa, 0x55]);
+        assert_eq!(display.ram()[5 * mock::WIDTH..][..32], data);
+    }
+
+    #[test]
+    fn last_segment_is_not_limited_by_buffer() {
+        let data: Vec<u8> = (0..20).collect();
+        let mut iface = interface().with_buffer_size::<6>();
+
+        block_on(iface.send_segments(&[Segment::Commands(&[0xb0, 0x10]), Segment::Data(&data)]))
+            .unwrap();
+
+        let mut expected = vec![0x80, 0xb0, 0x80, 0x10];
+        expected.extend(frame(DATA_BYTE, data).1);
+        assert_eq!(iface.release().transactions, vec![(ADDR, expected)]);
+    }
+
+    #[test]
+    fn bus_errors_are_classified() {
+        let mut iface = I2CInterface::new(mock::Ssd1306::new(0x3d), ADDR, DATA_BYTE);
+
+        assert!(matches!(
+            block_on(iface.send_commands(DataFormat::U8(&[0xaf]))),
+            Err(DisplayError::BusWriteError(BusErrorKind::NoAcknowledge))
+        ));
+    }
 }
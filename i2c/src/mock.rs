@@ -0,0 +1,346 @@
+//! Simulated SSD1306 display controller
+//!
+//! [`Ssd1306`] implements the `embedded-hal-async` I2C bus trait and interprets the bytes written
+//! to it like a 128x64 SSD1306 controller does: every transaction starts with a control byte
+//! selecting commands (0x00) or data (`data_byte` 0x40), optionally with the continuation bit
+//! (0x80) set to switch between both within a single transaction. Data is written into the
+//! display RAM according to the page, horizontal or vertical addressing mode, which allows to test
+//! [`I2CInterface`](crate::I2CInterface) together with display drivers on the host.
+
+use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+/// Width of the display in pixels
+pub const WIDTH: usize = 128;
+/// Height of the display in pixels
+pub const HEIGHT: usize = 64;
+
+const PAGES: u8 = (HEIGHT / 8) as u8;
+const COLUMNS: u8 = WIDTH as u8;
+
+/// Continuation bit of the control byte, only a single byte follows before the next control byte
+const CONTROL_CO: u8 = 0x80;
+/// Data/command selection bit of the control byte
+const CONTROL_DC: u8 = 0x40;
+
+/// Memory addressing mode selected with command 0x20
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressingMode {
+    /// Column address increments, wrapping to the next page at the end of the column range
+    Horizontal,
+    /// Page address increments, wrapping to the next column at the end of the page range
+    Vertical,
+    /// Column address increments within the current page
+    Page,
+}
+
+/// How the bytes following a control byte are interpreted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Next byte is a control byte
+    Control,
+    /// A single command or data byte follows before the next control byte
+    Single { data: bool },
+    /// All remaining bytes of the transaction are commands or data
+    Stream { data: bool },
+}
+
+/// Simulated 128x64 SSD1306 display controller connected via I2C
+#[derive(Clone, Debug)]
+pub struct Ssd1306 {
+    addr: u8,
+    ram: [u8; WIDTH * HEIGHT / 8],
+    mode: AddressingMode,
+    columns: (u8, u8),
+    pages: (u8, u8),
+    column: u8,
+    page: u8,
+    display_on: bool,
+    command: [u8; 7],
+    command_len: usize,
+}
+
+impl Ssd1306 {
+    /// Create new simulated controller responding to the given address, in its reset state
+    pub fn new(addr: u8) -> Self {
+        Self {
+            addr,
+            ram: [0; WIDTH * HEIGHT / 8],
+            mode: AddressingMode::Page,
+            columns: (0, COLUMNS - 1),
+            pages: (0, PAGES - 1),
+            column: 0,
+            page: 0,
+            display_on: false,
+            command: [0; 7],
+            command_len: 0,
+        }
+    }
+
+    /// Whether the pixel at the given position is lit, `false` if it's outside the display
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        x < WIDTH && y < HEIGHT && self.ram[y / 8 * WIDTH + x] & (1 << (y % 8)) != 0
+    }
+
+    /// Contents of the display RAM, one byte per column of 8 vertical pixels, page after page
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Currently selected memory addressing mode
+    pub fn addressing_mode(&self) -> AddressingMode {
+        self.mode
+    }
+
+    /// Whether the display has been switched on with command 0xAF
+    pub fn is_display_on(&self) -> bool {
+        self.display_on
+    }
+
+    fn write_command(&mut self, byte: u8) {
+        self.command[self.command_len] = byte;
+        self.command_len += 1;
+
+        let cmd = &self.command[..self.command_len];
+        if cmd.len() < 1 + parameter_count(cmd[0]) {
+            return;
+        }
+
+        match *cmd {
+            [c @ 0x00..=0x0f] => self.column = self.column & 0xf0 | c,
+            [c @ 0x10..=0x1f] => self.column = self.column & 0x0f | (c & 0x07) << 4,
+            [0x20, mode] => {
+                self.mode = match mode & 0x03 {
+                    0 => AddressingMode::Horizontal,
+                    1 => AddressingMode::Vertical,
+                    _ => AddressingMode::Page,
+                }
+            }
+            [0x21, start, end] => {
+                self.columns = (start & 0x7f, end & 0x7f);
+                self.column = self.columns.0;
+            }
+            [0x22, start, end] => {
+                self.pages = (start & 0x07, end & 0x07);
+                self.page = self.pages.0;
+            }
+            [0xae] => self.display_on = false,
+            [0xaf] => self.display_on = true,
+            [c @ 0xb0..=0xb7] => self.page = c & 0x07,
+            _ => {}
+        }
+
+        self.command_len = 0;
+    }
+
+    fn write_data(&mut self, byte: u8) {
+        if let Some(cell) = self
+            .ram
+            .get_mut(usize::from(self.page) * WIDTH + usize::from(self.column))
+        {
+            *cell = byte;
+        }
+
+        match self.mode {
+            AddressingMode::Page => {
+                self.column = if self.column >= COLUMNS - 1 {
+                    0
+                } else {
+                    self.column + 1
+                };
+            }
+            AddressingMode::Horizontal => {
+                if self.column < self.columns.1 {
+                    self.column += 1;
+                } else {
+                    self.column = self.columns.0;
+                    self.page = if self.page < self.pages.1 {
+                        self.page + 1
+                    } else {
+                        self.pages.0
+                    };
+                }
+            }
+            AddressingMode::Vertical => {
+                if self.page < self.pages.1 {
+                    self.page += 1;
+                } else {
+                    self.page = self.pages.0;
+                    self.column = if self.column < self.columns.1 {
+                        self.column + 1
+                    } else {
+                        self.columns.0
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Number of parameter bytes following the given command
+fn parameter_count(cmd: u8) -> usize {
+    match cmd {
+        0x20 | 0x81 | 0x8d | 0xa8 | 0xd3 | 0xd5 | 0xd9 | 0xda | 0xdb => 1,
+        0x21 | 0x22 | 0xa3 => 2,
+        0x29 | 0x2a => 5,
+        0x26 | 0x27 => 6,
+        _ => 0,
+    }
+}
+
+impl ErrorType for Ssd1306 {
+    type Error = ErrorKind;
+}
+
+impl I2c for Ssd1306 {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address != self.addr {
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+
+        let mut state = State::Control;
+
+        for op in operations {
+            match op {
+                Operation::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        state = match state {
+                            State::Control => {
+                                let data = byte & CONTROL_DC != 0;
+                                if byte & CONTROL_CO != 0 {
+                                    State::Single { data }
+                                } else {
+                                    State::Stream { data }
+                                }
+                            }
+                            State::Single { data } | State::Stream { data } => {
+                                if data {
+                                    self.write_data(byte);
+                                } else {
+                                    self.write_command(byte);
+                                }
+
+                                match state {
+                                    State::Single { .. } => State::Control,
+                                    _ => state,
+                                }
+                            }
+                        };
+                    }
+                }
+                // Reading returns the status byte, with the display off flag in bit 6
+                Operation::Read(buf) => {
+                    let status = if self.display_on { 0x00 } else { 0x40 };
+                    buf.fill(status);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::I2CInterface;
+    use display_interface::{DataFormat, WriteOnlyDataCommand};
+
+    #[test]
+    fn horizontal_addressing_through_interface() {
+        let mut display = Ssd1306::new(0x3c);
+        let mut iface = I2CInterface::new(&mut display, 0x3c, 0x40);
+        let data = [0xff; 6];
+
+        block_on(async {
+            // Horizontal mode in a 3x2 window at column 10, page 1
+            iface
+                .send_commands(DataFormat::U8(&[
+                    0x20, 0x00, 0x21, 10, 12, 0x22, 1, 2, 0xaf,
+                ]))
+                .await
+                .unwrap();
+            iface.send_data(DataFormat::U8(&data)).await.unwrap();
+        });
+
+        assert_eq!(display.addressing_mode(), AddressingMode::Horizontal);
+        assert!(display.is_display_on());
+        assert!(display.pixel(10, 8) && display.pixel(12, 23));
+        assert!(!display.pixel(9, 8) && !display.pixel(13, 8) && !display.pixel(10, 24));
+    }
+
+    #[test]
+    fn page_and_vertical_addressing() {
+        let mut display = Ssd1306::new(0x3c);
+
+        block_on(async {
+            // Page mode is the reset default: page 2, column 0x25
+            display
+                .write(0x3c, &[0x00, 0xb2, 0x05, 0x12])
+                .await
+                .unwrap();
+            display.write(0x3c, &[0x40, 0x01, 0x02]).await.unwrap();
+
+            // Vertical mode in columns 0..=1 and pages 0..=1
+            display
+                .write(0x3c, &[0x00, 0x20, 0x01, 0x21, 0, 1, 0x22, 0, 1])
+                .await
+                .unwrap();
+            display
+                .write(0x3c, &[0x40, 0x01, 0x01, 0x80])
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(display.ram()[2 * WIDTH + 0x25], 0x01);
+        assert_eq!(display.ram()[2 * WIDTH + 0x26], 0x02);
+        assert!(display.pixel(0, 0) && display.pixel(0, 8) && display.pixel(1, 7));
+    }
+
+    #[test]
+    fn higher_column_nibble_stays_in_range() {
+        let mut display = Ssd1306::new(0x3c);
+
+        block_on(async {
+            // 0x1f sets the higher nibble to 7, bit 3 is ignored like on the hardware
+            display
+                .write(0x3c, &[0x00, 0xb0, 0x0f, 0x1f])
+                .await
+                .unwrap();
+            display.write(0x3c, &[0x40, 0x01]).await.unwrap();
+        });
+
+        assert_eq!(display.ram()[0x7f], 0x01);
+        assert_eq!(display.ram()[WIDTH + 0x7f], 0x00);
+    }
+
+    #[test]
+    fn continuation_bit_mixes_commands_and_data() {
+        let mut display = Ssd1306::new(0x3c);
+
+        block_on(async {
+            display
+                .write(0x3c, &[0x80, 0xb7, 0xc0, 0xaa, 0x00, 0xaf])
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(display.ram()[7 * WIDTH], 0xaa);
+        assert!(display.is_display_on());
+    }
+
+    #[test]
+    fn other_address_is_not_acknowledged() {
+        let mut display = Ssd1306::new(0x3c);
+
+        assert_eq!(
+            block_on(display.write(0x3d, &[0x00, 0xaf])),
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+    }
+}
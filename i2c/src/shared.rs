@@ -0,0 +1,312 @@
+//! I2C interface sharing the bus with other devices
+
+use core::cell::{RefCell, RefMut};
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embedded_hal_async as hal;
+use hal::i2c::{AddressMode, SevenBitAddress, TenBitAddress};
+
+use display_interface::{DataFormat, DisplayError, ReadWriteDataCommand, WriteOnlyDataCommand};
+
+use crate::{I2CInterface, Segment};
+
+/// I2C communication interface on a bus shared with other devices
+///
+/// The bus is borrowed from the `RefCell` only for the duration of each call, so e.g. a touch
+/// controller on the same bus can be used in between. If the bus is already borrowed when a call
+/// starts, the call waits until it's released, yielding to the executor so the task holding the
+/// bus can make progress.
+pub struct SharedI2CInterface<'a, I2C, const N: usize = 17, A = SevenBitAddress> {
+    bus: &'a RefCell<I2C>,
+    config: I2CInterface<(), N, A>,
+}
+
+impl<'a, I2C> SharedI2CInterface<'a, I2C> {
+    /// Create new shared I2C interface for communication with a display driver
+    ///
+    /// The interface uses a transfer buffer of 17 bytes, see
+    /// [`with_buffer_size`](Self::with_buffer_size) to change it.
+    pub fn new(bus: &'a RefCell<I2C>, addr: u8, data_byte: u8) -> Self {
+        Self {
+            bus,
+            config: I2CInterface::new((), addr, data_byte),
+        }
+    }
+}
+
+impl<'a, I2C> SharedI2CInterface<'a, I2C, 17, TenBitAddress> {
+    /// Create new shared I2C interface for communication with a display driver with a 10-bit
+    /// address
+    pub fn new_ten_bit(bus: &'a RefCell<I2C>, addr: TenBitAddress, data_byte: u8) -> Self {
+        Self {
+            bus,
+            config: I2CInterface::new_ten_bit((), addr, data_byte),
+        }
+    }
+}
+
+impl<'a, I2C, const N: usize, A> SharedI2CInterface<'a, I2C, N, A>
+where
+    A: Copy,
+{
+    pub(crate) fn from_config(bus: &'a RefCell<I2C>, config: I2CInterface<(), N, A>) -> Self {
+        Self { bus, config }
+    }
+
+    /// Use a transfer buffer of `M` bytes, see [`I2CInterface::with_buffer_size`]
+    pub fn with_buffer_size<const M: usize>(self) -> SharedI2CInterface<'a, I2C, M, A> {
+        SharedI2CInterface {
+            bus: self.bus,
+            config: self.config.with_buffer_size::<M>(),
+        }
+    }
+
+    /// Set the control byte prefixed to commands, see [`I2CInterface::with_command_byte`]
+    pub fn with_command_byte(mut self, byte: u8) -> Self {
+        self.config = self.config.with_command_byte(byte);
+        self
+    }
+
+    /// Set the maximum number of command bytes sent per I2C transaction, see
+    /// [`I2CInterface::with_command_chunk_size`]
+    pub fn with_command_chunk_size(mut self, size: usize) -> Self {
+        self.config = self.config.with_command_chunk_size(size);
+        self
+    }
+
+    /// Send `U8` slices without copying them, see [`I2CInterface::with_zero_copy`]
+    pub fn with_zero_copy(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_zero_copy(enabled);
+        self
+    }
+
+    /// Consume the display interface and return the shared bus
+    pub fn release(self) -> &'a RefCell<I2C> {
+        self.bus
+    }
+
+    async fn lock(&self) -> RefMut<'a, I2C> {
+        let bus = self.bus;
+        poll_fn(|cx| match bus.try_borrow_mut() {
+            Ok(bus) => Poll::Ready(bus),
+            Err(_) => {
+                // There's no notification when the borrow ends, so ask to be polled again
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+// The bus is borrowed across awaits on purpose, other users see it as busy until the call is done
+#[allow(clippy::await_holding_refcell_ref)]
+impl<I2C, const N: usize, A> SharedI2CInterface<'_, I2C, N, A>
+where
+    I2C: hal::i2c::I2c<A>,
+    A: AddressMode + Copy,
+{
+    /// Send commands and data interleaved in a single I2C transaction, see
+    /// [`I2CInterface::send_segments`]
+    pub async fn send_segments(&mut self, segments: &[Segment<'_>]) -> Result<(), DisplayError> {
+        let mut bus = self.lock().await;
+        self.config.attach(&mut *bus).send_segments(segments).await
+    }
+}
+
+#[allow(clippy::await_holding_refcell_ref)]
+impl<I2C, const N: usize, A> WriteOnlyDataCommand for SharedI2CInterface<'_, I2C, N, A>
+where
+    I2C: hal::i2c::I2c<A>,
+    A: AddressMode + Copy,
+{
+    async fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        let mut bus = self.lock().await;
+        self.config.attach(&mut *bus).send_commands(cmds).await
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        let mut bus = self.lock().await;
+        self.config.attach(&mut *bus).send_data(buf).await
+    }
+
+    async fn send_command_with_data(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: DataFormat<'_>,
+    ) -> Result<(), DisplayError> {
+        // Keep the bus borrowed across both phases
+        let mut bus = self.lock().await;
+        self.config
+            .attach(&mut *bus)
+            .send_command_with_data(cmd, buf)
+            .await
+    }
+
+    async fn send_command_sequence<'a>(
+        &mut self,
+        sequence: impl IntoIterator<Item = (DataFormat<'a>, DataFormat<'a>)>,
+    ) -> Result<(), DisplayError> {
+        let mut bus = self.lock().await;
+        self.config
+            .attach(&mut *bus)
+            .send_command_sequence(sequence)
+            .await
+    }
+}
+
+#[allow(clippy::await_holding_refcell_ref)]
+impl<I2C, const N: usize, A> ReadWriteDataCommand for SharedI2CInterface<'_, I2C, N, A>
+where
+    I2C: hal::i2c::I2c<A>,
+    A: AddressMode + Copy,
+{
+    async fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        let mut bus = self.lock().await;
+        self.config.attach(&mut *bus).read_data(buf).await
+    }
+
+    async fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError> {
+        let mut bus = self.lock().await;
+        self.config
+            .attach(&mut *bus)
+            .send_command_read(cmd, buf)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use futures::executor::block_on;
+    use futures::future::join;
+    use hal::i2c::Operation;
+
+    use super::*;
+    use crate::mock::Ssd1306;
+
+    /// I2C bus recording the bytes of every write, yielding once per transaction so other futures
+    /// can run while the bus is borrowed
+    #[derive(Default)]
+    struct YieldingI2c {
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl hal::i2c::ErrorType for YieldingI2c {
+        type Error = hal::i2c::ErrorKind;
+    }
+
+    impl hal::i2c::I2c<u8> for YieldingI2c {
+        async fn transaction(
+            &mut self,
+            _address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            let mut written = Vec::new();
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => written.extend_from_slice(bytes),
+                    Operation::Read(_) => return Err(hal::i2c::ErrorKind::Other),
+                }
+            }
+            self.writes.push(written);
+            yield_once().await;
+            Ok(())
+        }
+    }
+
+    /// Return `Pending` once to let other futures run
+    async fn yield_once() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    #[allow(clippy::await_holding_refcell_ref)]
+    fn shares_bus_between_calls() {
+        let bus = RefCell::new(Ssd1306::new(0x3c));
+        let mut iface = SharedI2CInterface::new(&bus, 0x3c, 0x40);
+
+        block_on(iface.send_commands(DataFormat::U8(&[0xaf]))).unwrap();
+        // Another device can use the bus in between
+        assert!(bus.borrow().is_display_on());
+        block_on(iface.send_data(DataFormat::U8(&[0xff]))).unwrap();
+        assert!(bus.borrow().pixel(0, 7));
+
+        // A call starting while the bus is borrowed waits until it's released
+        let held = async {
+            let guard = bus.borrow_mut();
+            yield_once().await;
+            assert!(guard.is_display_on());
+        };
+        block_on(join(held, iface.send_commands(DataFormat::U8(&[0xae]))))
+            .1
+            .unwrap();
+        assert!(!bus.borrow().is_display_on());
+    }
+
+    #[test]
+    fn command_with_data_keeps_bus_borrowed() {
+        let bus = RefCell::new(YieldingI2c::default());
+        let mut iface = SharedI2CInterface::new(&bus, 0x3c, 0x40);
+        let mut borrowed = Vec::new();
+
+        // Try to borrow the bus while the command and the data are written and after the call
+        let contender = async {
+            for _ in 0..3 {
+                borrowed.push(bus.try_borrow_mut().is_err());
+                yield_once().await;
+            }
+        };
+        let call =
+            iface.send_command_with_data(DataFormat::U8(&[0x21, 0, 127]), DataFormat::U8(&[0xff]));
+        block_on(join(call, contender)).0.unwrap();
+
+        assert_eq!(borrowed, [true, true, false]);
+        assert_eq!(
+            bus.into_inner().writes,
+            vec![vec![0x00, 0x21, 0, 127], vec![0x40, 0xff]]
+        );
+    }
+
+    #[test]
+    fn command_sequence_keeps_bus_borrowed() {
+        let bus = RefCell::new(Ssd1306::new(0x3c));
+        let mut iface = SharedI2CInterface::new(&bus, 0x3c, 0x40);
+        let mut borrowed = Vec::new();
+
+        // The sequence is only consumed once the override holds the bus, the provided method would
+        // release it between the items
+        let sequence = [(0xb0, 0x01), (0xb1, 0x02)].iter().map(|(page, pixels)| {
+            borrowed.push(bus.try_borrow_mut().is_err());
+            (
+                DataFormat::U8(core::slice::from_ref(page)),
+                DataFormat::U8(core::slice::from_ref(pixels)),
+            )
+        });
+        block_on(iface.send_command_sequence(sequence)).unwrap();
+
+        assert_eq!(borrowed, [true, true]);
+        let display = bus.borrow();
+        assert!(display.pixel(0, 0));
+        assert!(display.pixel(1, 9));
+    }
+}
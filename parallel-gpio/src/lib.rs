@@ -0,0 +1,478 @@
+#![no_std]
+
+//! Generic parallel GPIO interface for display drivers
+use embedded_hal::digital::OutputPin;
+
+use display_interface::BusErrorKind;
+pub use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+type Result<T = ()> = core::result::Result<T, DisplayError>;
+
+/// This trait represents the data pins of a parallel bus.
+///
+/// See [Generic8BitBus] and [Generic16BitBus] for generic implementations.
+pub trait OutputBus {
+    /// [u8] for 8-bit buses, [u16] for 16-bit buses, etc.
+    type Word: Copy;
+
+    /// Put the given word on the data lines of the bus
+    fn set_value(&mut self, value: Self::Word) -> Result;
+}
+
+macro_rules! generic_bus {
+    ($GenericxBitBus:ident { type Word = $Word:ident; Pins {$($PX:ident => $x:tt,)*}}) => {
+        /// A generic implementation of [OutputBus] using [OutputPin]s
+        pub struct $GenericxBitBus<$($PX, )*> {
+            pins: ($($PX, )*),
+            last: Option<$Word>,
+        }
+
+        impl<$($PX, )*> $GenericxBitBus<$($PX, )*>
+        where
+            $($PX: OutputPin, )*
+        {
+            /// Creates a new bus. This does not change the state of the pins.
+            ///
+            /// The first pin in the tuple is the least significant bit.
+            pub fn new(pins: ($($PX, )*)) -> Self {
+                Self { pins, last: None }
+            }
+
+            /// Consumes the bus and returns the pins. This does not change the state of the pins.
+            pub fn release(self) -> ($($PX, )*) {
+                self.pins
+            }
+        }
+
+        impl<$($PX, )*> OutputBus for $GenericxBitBus<$($PX, )*>
+        where
+            $($PX: OutputPin, )*
+        {
+            type Word = $Word;
+
+            fn set_value(&mut self, value: Self::Word) -> Result {
+                if self.last == Some(value) {
+                    // Don't need to update the pins because they're already set
+                    return Ok(());
+                }
+
+                self.last = Some(value);
+
+                $(
+                    let bit = 1 << $x;
+                    if value & bit != 0 {
+                        self.pins.$x.set_high()
+                    } else {
+                        self.pins.$x.set_low()
+                    }
+                    .map_err(|_| DisplayError::BusWriteError(BusErrorKind::Other))?;
+                )*
+
+                Ok(())
+            }
+        }
+
+        impl<$($PX, )*> From<($($PX, )*)> for $GenericxBitBus<$($PX, )*>
+        where
+            $($PX: OutputPin, )*
+        {
+            fn from(pins: ($($PX, )*)) -> Self {
+                Self::new(pins)
+            }
+        }
+    };
+}
+
+generic_bus! {
+    Generic8BitBus {
+        type Word = u8;
+        Pins {
+            P0 => 0,
+            P1 => 1,
+            P2 => 2,
+            P3 => 3,
+            P4 => 4,
+            P5 => 5,
+            P6 => 6,
+            P7 => 7,
+        }
+    }
+}
+
+generic_bus! {
+    Generic16BitBus {
+        type Word = u16;
+        Pins {
+            P0 => 0,
+            P1 => 1,
+            P2 => 2,
+            P3 => 3,
+            P4 => 4,
+            P5 => 5,
+            P6 => 6,
+            P7 => 7,
+            P8 => 8,
+            P9 => 9,
+            P10 => 10,
+            P11 => 11,
+            P12 => 12,
+            P13 => 13,
+            P14 => 14,
+            P15 => 15,
+        }
+    }
+}
+
+/// Parallel 8 Bit communication interface
+///
+/// This interface implements an 8-Bit "8080" style write-only display interface using any
+/// 8-bit [OutputBus] implementation as well as one
+/// `OutputPin` for the data/command selection and one `OutputPin` for the write-enable flag.
+///
+/// All pins are supposed to be high-active, high for the D/C pin meaning "data" and the
+/// write-enable being pulled low before the setting of the bits and supposed to be sampled at a
+/// low to high edge.
+pub struct PGPIO8BitInterface<BUS, DC, WR> {
+    bus: BUS,
+    dc: DC,
+    wr: WR,
+}
+
+impl<BUS, DC, WR> PGPIO8BitInterface<BUS, DC, WR>
+where
+    BUS: OutputBus<Word = u8>,
+    DC: OutputPin,
+    WR: OutputPin,
+{
+    /// Create new parallel GPIO interface for communication with a display driver
+    pub fn new(bus: BUS, dc: DC, wr: WR) -> Self {
+        Self { bus, dc, wr }
+    }
+
+    /// Consume the display interface and return
+    /// the bus and GPIO pins used by it
+    pub fn release(self) -> (BUS, DC, WR) {
+        (self.bus, self.dc, self.wr)
+    }
+
+    fn send_byte(&mut self, byte: u8) -> Result {
+        self.wr
+            .set_low()
+            .map_err(|_| DisplayError::BusWriteError(BusErrorKind::Other))?;
+        self.bus.set_value(byte)?;
+        self.wr
+            .set_high()
+            .map_err(|_| DisplayError::BusWriteError(BusErrorKind::Other))
+    }
+
+    fn send_bytes(&mut self, bytes: impl IntoIterator<Item = u8>) -> Result {
+        bytes.into_iter().try_for_each(|byte| self.send_byte(byte))
+    }
+
+    fn send_format(&mut self, words: DataFormat<'_>) -> Result {
+        self.send_bytes(words.into_bytes())
+    }
+}
+
+impl<BUS, DC, WR> WriteOnlyDataCommand for PGPIO8BitInterface<BUS, DC, WR>
+where
+    BUS: OutputBus<Word = u8>,
+    DC: OutputPin,
+    WR: OutputPin,
+{
+    async fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result {
+        self.dc.set_low().map_err(|_| DisplayError::DCError)?;
+        self.send_format(cmds)
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result {
+        self.dc.set_high().map_err(|_| DisplayError::DCError)?;
+        self.send_format(buf)
+    }
+}
+
+/// Parallel 16 Bit communication interface
+///
+/// This interface implements a 16-Bit "8080" style write-only display interface using any
+/// 16-bit [OutputBus] implementation as well as one
+/// `OutputPin` for the data/command selection and one `OutputPin` for the write-enable flag.
+///
+/// All pins are supposed to be high-active, high for the D/C pin meaning "data" and the
+/// write-enable being pulled low before the setting of the bits and supposed to be sampled at a
+/// low to high edge.
+///
+/// 8-bit data is zero-extended, with every byte occupying the lower half of a bus word, and so are
+/// the bytes of 24-bit, RGB666 and packed pixels. 16-bit data is written a full word at a time, so
+/// the byte order of the `U16BE`/`U16LE` variants has no effect on this bus. 32-bit data is written
+/// as two words, the most significant half first for the big endian variants and the least
+/// significant half first for the little endian ones.
+pub struct PGPIO16BitInterface<BUS, DC, WR> {
+    bus: BUS,
+    dc: DC,
+    wr: WR,
+}
+
+impl<BUS, DC, WR> PGPIO16BitInterface<BUS, DC, WR>
+where
+    BUS: OutputBus<Word = u16>,
+    DC: OutputPin,
+    WR: OutputPin,
+{
+    /// Create new parallel GPIO interface for communication with a display driver
+    pub fn new(bus: BUS, dc: DC, wr: WR) -> Self {
+        Self { bus, dc, wr }
+    }
+
+    /// Consume the display interface and return
+    /// the bus and GPIO pins used by it
+    pub fn release(self) -> (BUS, DC, WR) {
+        (self.bus, self.dc, self.wr)
+    }
+
+    fn send_word(&mut self, word: u16) -> Result {
+        self.wr
+            .set_low()
+            .map_err(|_| DisplayError::BusWriteError(BusErrorKind::Other))?;
+        self.bus.set_value(word)?;
+        self.wr
+            .set_high()
+            .map_err(|_| DisplayError::BusWriteError(BusErrorKind::Other))
+    }
+
+    fn send_words(&mut self, words: impl IntoIterator<Item = u16>) -> Result {
+        words.into_iter().try_for_each(|word| self.send_word(word))
+    }
+
+    fn send_format(&mut self, words: DataFormat<'_>) -> Result {
+        match words {
+            DataFormat::U8(slice) => self.send_words(slice.iter().copied().map(u16::from)),
+            DataFormat::U16(slice) => self.send_words(slice.iter().copied()),
+            DataFormat::U16BE(slice) | DataFormat::U16LE(slice) => {
+                self.send_words(slice.iter().copied())
+            }
+            DataFormat::U8Iter(iter) => self.send_words(iter.map(u16::from)),
+            DataFormat::U16BEIter(iter) | DataFormat::U16LEIter(iter) => self.send_words(iter),
+            DataFormat::U16BERef(slice) | DataFormat::U16LERef(slice) => {
+                self.send_words(slice.iter().copied())
+            }
+            DataFormat::U32(slice) => self.send_words(slice.iter().copied().flat_map(ne_halves)),
+            DataFormat::U32BE(slice) => self.send_words(slice.iter().copied().flat_map(be_halves)),
+            DataFormat::U32LE(slice) => self.send_words(slice.iter().copied().flat_map(le_halves)),
+            DataFormat::U32BEIter(iter) => self.send_words(iter.flat_map(be_halves)),
+            DataFormat::U32LEIter(iter) => self.send_words(iter.flat_map(le_halves)),
+            words @ (DataFormat::U24(_)
+            | DataFormat::U24Iter(_)
+            | DataFormat::RGB666(_)
+            | DataFormat::RGB666Iter(_)
+            | DataFormat::PackedIter(..)
+            | DataFormat::MonoIter(..)) => self.send_words(words.into_bytes().map(u16::from)),
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+}
+
+/// Split a 32-bit word into two bus words, most significant half first
+fn be_halves(word: u32) -> [u16; 2] {
+    [(word >> 16) as u16, word as u16]
+}
+
+/// Split a 32-bit word into two bus words, least significant half first
+fn le_halves(word: u32) -> [u16; 2] {
+    [word as u16, (word >> 16) as u16]
+}
+
+/// Split a 32-bit word into two bus words in the order of the system's endianess
+fn ne_halves(word: u32) -> [u16; 2] {
+    if cfg!(target_endian = "big") {
+        be_halves(word)
+    } else {
+        le_halves(word)
+    }
+}
+
+impl<BUS, DC, WR> WriteOnlyDataCommand for PGPIO16BitInterface<BUS, DC, WR>
+where
+    BUS: OutputBus<Word = u16>,
+    DC: OutputPin,
+    WR: OutputPin,
+{
+    async fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result {
+        self.dc.set_low().map_err(|_| DisplayError::DCError)?;
+        self.send_format(cmds)
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result {
+        self.dc.set_high().map_err(|_| DisplayError::DCError)?;
+        self.send_format(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal::digital::ErrorType;
+    use futures::executor::block_on;
+
+    use super::*;
+    use display_interface::packed::BitOrder;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Event {
+        Dc(bool),
+        Wr(bool),
+        Bus(u16),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    /// Bus recording every value put on the data lines
+    struct MockBus<W>(Log, core::marker::PhantomData<W>);
+
+    impl<W: Copy + Into<u16>> OutputBus for MockBus<W> {
+        type Word = W;
+
+        fn set_value(&mut self, value: W) -> Result {
+            self.0.borrow_mut().push(Event::Bus(value.into()));
+            Ok(())
+        }
+    }
+
+    /// Pin recording every level change
+    struct MockPin(Log, fn(bool) -> Event);
+
+    impl ErrorType for MockPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockPin {
+        fn set_low(&mut self) -> core::result::Result<(), Infallible> {
+            self.0.borrow_mut().push((self.1)(false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> core::result::Result<(), Infallible> {
+            self.0.borrow_mut().push((self.1)(true));
+            Ok(())
+        }
+    }
+
+    fn parts<W>() -> (Log, MockBus<W>, MockPin, MockPin) {
+        let log = Log::default();
+        (
+            log.clone(),
+            MockBus(log.clone(), core::marker::PhantomData),
+            MockPin(log.clone(), Event::Dc),
+            MockPin(log, Event::Wr),
+        )
+    }
+
+    /// Events for strobing the given words onto the bus
+    fn strobed(words: impl IntoIterator<Item = u16>) -> Vec<Event> {
+        words
+            .into_iter()
+            .flat_map(|w| [Event::Wr(false), Event::Bus(w), Event::Wr(true)])
+            .collect()
+    }
+
+    #[test]
+    fn commands_and_data_set_dc_and_strobe_wr() {
+        let (log, bus, dc, wr) = parts::<u8>();
+        let mut iface = PGPIO8BitInterface::new(bus, dc, wr);
+
+        block_on(iface.send_commands(DataFormat::U8(&[0x2c]))).unwrap();
+        block_on(iface.send_data(DataFormat::U8(&[0x01, 0x02]))).unwrap();
+
+        let mut expected = vec![Event::Dc(false)];
+        expected.extend(strobed([0x2c]));
+        expected.push(Event::Dc(true));
+        expected.extend(strobed([0x01, 0x02]));
+        assert_eq!(*log.borrow(), expected);
+    }
+
+    #[test]
+    fn u16_data_is_split_into_bytes_on_8bit_bus() {
+        let (log, bus, dc, wr) = parts::<u8>();
+        let mut iface = PGPIO8BitInterface::new(bus, dc, wr);
+        let mut be = [0x0102, 0x0304];
+        let mut le = [0x0102, 0x0304];
+        let mut be_iter = core::iter::once(0x0506);
+        let mut le_iter = core::iter::once(0x0506);
+
+        block_on(iface.send_data(DataFormat::U16BE(&mut be))).unwrap();
+        block_on(iface.send_data(DataFormat::U16LE(&mut le))).unwrap();
+        block_on(iface.send_data(DataFormat::U16BEIter(&mut be_iter))).unwrap();
+        block_on(iface.send_data(DataFormat::U16LEIter(&mut le_iter))).unwrap();
+
+        let mut expected = Vec::new();
+        for bytes in [
+            &[0x01u8, 0x02, 0x03, 0x04][..],
+            &[0x02, 0x01, 0x04, 0x03],
+            &[0x05, 0x06],
+            &[0x06, 0x05],
+        ] {
+            expected.push(Event::Dc(true));
+            expected.extend(strobed(bytes.iter().copied().map(u16::from)));
+        }
+        assert_eq!(*log.borrow(), expected);
+    }
+
+    #[test]
+    fn pixel_bytes_are_zero_extended_on_16bit_bus() {
+        let (log, bus, dc, wr) = parts::<u16>();
+        let mut iface = PGPIO16BitInterface::new(bus, dc, wr);
+
+        block_on(iface.send_data(DataFormat::U24(&[[0x01, 0x02, 0x03]]))).unwrap();
+        block_on(iface.send_data(DataFormat::RGB666(&[0x3_f000]))).unwrap();
+        block_on(iface.send_data(DataFormat::MonoIter(
+            BitOrder::MsbFirst,
+            &mut [true, false, true].iter().copied(),
+        )))
+        .unwrap();
+
+        let mut expected = vec![Event::Dc(true)];
+        expected.extend(strobed([0x01, 0x02, 0x03]));
+        expected.push(Event::Dc(true));
+        expected.extend(strobed([0xfc, 0x00, 0x00]));
+        expected.push(Event::Dc(true));
+        expected.extend(strobed([0xa0]));
+        assert_eq!(*log.borrow(), expected);
+    }
+
+    #[test]
+    fn u32_data_is_split_into_words_on_16bit_bus() {
+        let (log, bus, dc, wr) = parts::<u16>();
+        let mut iface = PGPIO16BitInterface::new(bus, dc, wr);
+        let mut le_iter = core::iter::once(0x0102_0304);
+
+        block_on(iface.send_data(DataFormat::U32BE(&mut [0x0102_0304]))).unwrap();
+        block_on(iface.send_data(DataFormat::U32LEIter(&mut le_iter))).unwrap();
+
+        let mut expected = vec![Event::Dc(true)];
+        expected.extend(strobed([0x0102, 0x0304]));
+        expected.push(Event::Dc(true));
+        expected.extend(strobed([0x0304, 0x0102]));
+        assert_eq!(*log.borrow(), expected);
+    }
+
+    #[test]
+    fn u16_data_is_sent_as_words_on_16bit_bus() {
+        let (log, bus, dc, wr) = parts::<u16>();
+        let mut iface = PGPIO16BitInterface::new(bus, dc, wr);
+
+        block_on(iface.send_commands(DataFormat::U8(&[0x2c]))).unwrap();
+        block_on(iface.send_data(DataFormat::U16BE(&mut [0x0102]))).unwrap();
+
+        let mut expected = vec![Event::Dc(false)];
+        expected.extend(strobed([0x002c]));
+        expected.push(Event::Dc(true));
+        expected.extend(strobed([0x0102]));
+        assert_eq!(*log.borrow(), expected);
+    }
+}
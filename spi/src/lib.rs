@@ -0,0 +1,243 @@
+#![no_std]
+
+//! Generic SPI interface for display drivers
+use byte_slice_cast::AsByteSlice;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::Error;
+use embedded_hal_async as hal;
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+async fn send_u8<SPI>(spi: &mut SPI, words: DataFormat<'_>) -> Result<(), DisplayError>
+where
+    SPI: hal::spi::SpiDevice,
+{
+    match words {
+        DataFormat::U8(slice) => spi
+            .write(slice)
+            .await
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into())),
+        DataFormat::U16(slice) => spi
+            .write(slice.as_byte_slice())
+            .await
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into())),
+        DataFormat::U16LE(slice) => {
+            for v in slice.iter_mut() {
+                *v = v.to_le();
+            }
+            spi.write(slice.as_byte_slice())
+                .await
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+        }
+        DataFormat::U16BE(slice) => {
+            for v in slice.iter_mut() {
+                *v = v.to_be();
+            }
+            spi.write(slice.as_byte_slice())
+                .await
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+        }
+        DataFormat::U32(slice) => spi
+            .write(slice.as_byte_slice())
+            .await
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into())),
+        DataFormat::U32LE(slice) => {
+            for v in slice.iter_mut() {
+                *v = v.to_le();
+            }
+            spi.write(slice.as_byte_slice())
+                .await
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+        }
+        DataFormat::U32BE(slice) => {
+            for v in slice.iter_mut() {
+                *v = v.to_be();
+            }
+            spi.write(slice.as_byte_slice())
+                .await
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))
+        }
+        words => send_u8_iter(spi, words.into_bytes()).await,
+    }
+}
+
+/// Send bytes, batching them to avoid a transfer per byte
+async fn send_u8_iter<SPI>(
+    spi: &mut SPI,
+    iter: impl Iterator<Item = u8>,
+) -> Result<(), DisplayError>
+where
+    SPI: hal::spi::SpiDevice,
+{
+    let mut buf = [0; 32];
+    let mut i = 0;
+
+    for v in iter {
+        buf[i] = v;
+        i += 1;
+
+        if i == buf.len() {
+            spi.write(&buf)
+                .await
+                .map_err(|e| DisplayError::BusWriteError(e.kind().into()))?;
+            i = 0;
+        }
+    }
+
+    if i > 0 {
+        spi.write(&buf[..i])
+            .await
+            .map_err(|e| DisplayError::BusWriteError(e.kind().into()))?;
+    }
+
+    Ok(())
+}
+
+/// SPI display interface.
+///
+/// This combines the SPI device and a data/command pin. Chip select is managed by the
+/// [`SpiDevice`](hal::spi::SpiDevice) implementation for the duration of each transfer.
+pub struct SPIInterface<SPI, DC> {
+    spi: SPI,
+    dc: DC,
+}
+
+impl<SPI, DC> SPIInterface<SPI, DC>
+where
+    SPI: hal::spi::SpiDevice,
+    DC: OutputPin,
+{
+    /// Create new SPI interface for communication with a display driver
+    pub fn new(spi: SPI, dc: DC) -> Self {
+        Self { spi, dc }
+    }
+
+    /// Consume the display interface and return
+    /// the underlying peripherial driver and GPIO pins used by it
+    pub fn release(self) -> (SPI, DC) {
+        (self.spi, self.dc)
+    }
+}
+
+impl<SPI, DC> WriteOnlyDataCommand for SPIInterface<SPI, DC>
+where
+    SPI: hal::spi::SpiDevice,
+    DC: OutputPin,
+{
+    async fn send_commands(&mut self, cmds: DataFormat<'_>) -> Result<(), DisplayError> {
+        // 1 = data, 0 = command
+        self.dc.set_low().map_err(|_| DisplayError::DCError)?;
+
+        // Send words over SPI
+        send_u8(&mut self.spi, cmds).await
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        // 1 = data, 0 = command
+        self.dc.set_high().map_err(|_| DisplayError::DCError)?;
+
+        // Send words over SPI
+        send_u8(&mut self.spi, buf).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::convert::Infallible;
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal::digital::ErrorType;
+    use futures::executor::block_on;
+    use hal::spi::Operation;
+
+    use super::*;
+
+    /// SPI device recording the bytes of every write and rejecting all other operations
+    #[derive(Default)]
+    struct MockSpi {
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl hal::spi::ErrorType for MockSpi {
+        type Error = hal::spi::ErrorKind;
+    }
+
+    impl hal::spi::SpiDevice for MockSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            for op in operations {
+                match op {
+                    Operation::Write(bytes) => self.writes.push(bytes.to_vec()),
+                    _ => return Err(hal::spi::ErrorKind::Other),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    struct MockPin;
+
+    impl ErrorType for MockPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockPin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    fn interface() -> SPIInterface<MockSpi, MockPin> {
+        SPIInterface::new(MockSpi::default(), MockPin)
+    }
+
+    #[test]
+    fn u16_slices_are_swapped_in_place() {
+        let mut be = [0x0102, 0x0304];
+        let mut le = [0x0102, 0x0304];
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U16BE(&mut be))).unwrap();
+        block_on(iface.send_data(DataFormat::U16LE(&mut le))).unwrap();
+
+        assert_eq!(
+            iface.release().0.writes,
+            vec![vec![0x01, 0x02, 0x03, 0x04], vec![0x02, 0x01, 0x04, 0x03]]
+        );
+    }
+
+    #[test]
+    fn iterators_are_batched() {
+        let mut bytes = 0..70;
+        let mut words = 0..40u16;
+        let mut iface = interface();
+
+        block_on(iface.send_data(DataFormat::U8Iter(&mut bytes))).unwrap();
+        block_on(iface.send_data(DataFormat::U16BEIter(&mut words))).unwrap();
+
+        let writes = iface.release().0.writes;
+        let be = |range: core::ops::Range<u16>| -> Vec<u8> {
+            range.flat_map(u16::to_be_bytes).collect()
+        };
+        assert_eq!(
+            writes,
+            vec![
+                (0..32).collect::<Vec<u8>>(),
+                (32..64).collect(),
+                (64..70).collect(),
+                be(0..16),
+                be(16..32),
+                be(32..40),
+            ]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Blocking display interface
+//!
+//! This module contains blocking versions of [`WriteOnlyDataCommand`] and [`ReadWriteDataCommand`]
+//! for display drivers which don't use async, together with adapters converting between the
+//! blocking and the async traits.
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use crate::{DataFormat, DisplayError};
+
+/// Blocking counterpart of [`crate::WriteOnlyDataCommand`]
+///
+/// This trait implements a write-only interface for a display which has separate data and command
+/// modes. It is the responsibility of implementations to activate the correct mode in their
+/// implementation when corresponding method is called.
+pub trait WriteOnlyDataCommand {
+    /// Send a batch of commands to display
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError>;
+
+    /// Send pixel data to display
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError>;
+
+    /// Send a batch of commands followed by data, see
+    /// [`crate::WriteOnlyDataCommand::send_command_with_data`]
+    fn send_command_with_data(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: DataFormat<'_>,
+    ) -> Result<(), DisplayError> {
+        self.send_commands(cmd)?;
+        self.send_data(buf)
+    }
+
+    /// Send a sequence of command batches each followed by data, see
+    /// [`crate::WriteOnlyDataCommand::send_command_sequence`]
+    fn send_command_sequence<'a>(
+        &mut self,
+        sequence: impl IntoIterator<Item = (DataFormat<'a>, DataFormat<'a>)>,
+    ) -> Result<(), DisplayError> {
+        for (cmd, buf) in sequence {
+            self.send_command_with_data(cmd, buf)?;
+        }
+        Ok(())
+    }
+}
+
+/// Blocking counterpart of [`crate::ReadWriteDataCommand`]
+///
+/// This trait extends [`WriteOnlyDataCommand`] for displays which allow reading back data.
+pub trait ReadWriteDataCommand: WriteOnlyDataCommand {
+    /// Read data from display, filling the whole buffer
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError>;
+
+    /// Send a batch of commands to display and read the response into the buffer
+    fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError>;
+}
+
+/// Adapter implementing the blocking display interface traits for an async display interface
+///
+/// Every call is driven to completion by busy polling the returned future, so this is only suited
+/// for async interfaces which make progress without relying on being woken up, e.g. on top of
+/// HALs which poll their peripherals.
+pub struct BlockingAdapter<DI>(pub DI);
+
+impl<DI> BlockingAdapter<DI> {
+    /// Consume the adapter and return the wrapped display interface
+    pub fn release(self) -> DI {
+        self.0
+    }
+}
+
+impl<DI> WriteOnlyDataCommand for BlockingAdapter<DI>
+where
+    DI: crate::WriteOnlyDataCommand,
+{
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        block_on(self.0.send_commands(cmd))
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        block_on(self.0.send_data(buf))
+    }
+
+    fn send_command_with_data(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: DataFormat<'_>,
+    ) -> Result<(), DisplayError> {
+        block_on(self.0.send_command_with_data(cmd, buf))
+    }
+
+    fn send_command_sequence<'a>(
+        &mut self,
+        sequence: impl IntoIterator<Item = (DataFormat<'a>, DataFormat<'a>)>,
+    ) -> Result<(), DisplayError> {
+        block_on(self.0.send_command_sequence(sequence))
+    }
+}
+
+impl<DI> ReadWriteDataCommand for BlockingAdapter<DI>
+where
+    DI: crate::ReadWriteDataCommand,
+{
+    fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        block_on(self.0.read_data(buf))
+    }
+
+    fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError> {
+        block_on(self.0.send_command_read(cmd, buf))
+    }
+}
+
+/// Adapter implementing the async display interface traits for a blocking display interface
+///
+/// The returned futures complete on their first poll, all the work happens synchronously.
+pub struct AsyncAdapter<DI>(pub DI);
+
+impl<DI> AsyncAdapter<DI> {
+    /// Consume the adapter and return the wrapped display interface
+    pub fn release(self) -> DI {
+        self.0
+    }
+}
+
+impl<DI> crate::WriteOnlyDataCommand for AsyncAdapter<DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    async fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.0.send_commands(cmd)
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.0.send_data(buf)
+    }
+
+    async fn send_command_with_data(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: DataFormat<'_>,
+    ) -> Result<(), DisplayError> {
+        self.0.send_command_with_data(cmd, buf)
+    }
+
+    async fn send_command_sequence<'a>(
+        &mut self,
+        sequence: impl IntoIterator<Item = (DataFormat<'a>, DataFormat<'a>)>,
+    ) -> Result<(), DisplayError> {
+        self.0.send_command_sequence(sequence)
+    }
+}
+
+impl<DI> crate::ReadWriteDataCommand for AsyncAdapter<DI>
+where
+    DI: ReadWriteDataCommand,
+{
+    async fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+        self.0.read_data(buf)
+    }
+
+    async fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError> {
+        self.0.send_command_read(cmd, buf)
+    }
+}
+
+/// Run the future to completion by polling it until it's ready
+pub(crate) fn block_on<F: Future>(fut: F) -> F::Output {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+    const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+
+    // SAFETY: the vtable functions don't do anything, so any data pointer is fine
+    let waker = unsafe { Waker::from_raw(RAW) };
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = pin!(fut);
+
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// Blocking interface recording the sent data and reading it back
+    #[derive(Default)]
+    struct Recorder {
+        data: Vec<u8>,
+    }
+
+    impl WriteOnlyDataCommand for Recorder {
+        fn send_commands(&mut self, _cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+            Err(DisplayError::DataFormatNotImplemented)
+        }
+
+        fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+            match buf {
+                DataFormat::U8Iter(iter) => self.data.extend(iter),
+                _ => return Err(DisplayError::DataFormatNotImplemented),
+            }
+            Ok(())
+        }
+    }
+
+    impl ReadWriteDataCommand for Recorder {
+        fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError> {
+            let len = buf.len();
+            buf.copy_from_slice(&self.data[..len]);
+            Ok(())
+        }
+
+        fn send_command_read(
+            &mut self,
+            _cmd: DataFormat<'_>,
+            _buf: &mut [u8],
+        ) -> Result<(), DisplayError> {
+            Err(DisplayError::DataFormatNotImplemented)
+        }
+    }
+
+    #[test]
+    fn adapters_round_trip() {
+        let mut iface = BlockingAdapter(AsyncAdapter(Recorder::default()));
+
+        iface.send_data(DataFormat::U8Iter(&mut (0..4))).unwrap();
+        assert!(iface.send_commands(DataFormat::U8(&[0x2c])).is_err());
+
+        let mut buf = [0; 2];
+        iface.read_data(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1]);
+
+        assert_eq!(iface.release().release().data, [0, 1, 2, 3]);
+    }
+}
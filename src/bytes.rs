@@ -0,0 +1,172 @@
+//! Serialization of [`DataFormat`] into bytes
+//!
+//! Display interface implementations for byte oriented buses can send every variant of
+//! [`DataFormat`] by iterating over [`DataFormat::into_bytes`], which yields the bytes in the order
+//! they're sent on the wire, and only special case the variants they can send more efficiently,
+//! e.g. `U8` slices in a single transfer.
+
+use core::iter::{Copied, FlatMap, Flatten, Map};
+use core::slice;
+
+use crate::packed::{BitDepth, PackPixels};
+use crate::{rgb666_to_bytes, DataFormat};
+
+type Words<'a, T> = Copied<slice::Iter<'a, T>>;
+type DynIter<'a, T> = &'a mut dyn Iterator<Item = T>;
+type Serialized<I, T, const B: usize> = FlatMap<I, [u8; B], fn(T) -> [u8; B]>;
+type MonoPixels<'a> = Map<DynIter<'a, bool>, fn(bool) -> u8>;
+
+/// Iterator over the bytes of a [`DataFormat`], see [`DataFormat::into_bytes`]
+pub struct Bytes<'a>(Inner<'a>);
+
+enum Inner<'a> {
+    U8(Words<'a, u8>),
+    U8Iter(DynIter<'a, u8>),
+    U16(Serialized<Words<'a, u16>, u16, 2>),
+    U16Iter(Serialized<DynIter<'a, u16>, u16, 2>),
+    U24(Flatten<Words<'a, [u8; 3]>>),
+    U24Iter(Flatten<DynIter<'a, [u8; 3]>>),
+    U32(Serialized<Words<'a, u32>, u32, 4>),
+    U32Iter(Serialized<DynIter<'a, u32>, u32, 4>),
+    RGB666(Serialized<Words<'a, u32>, u32, 3>),
+    RGB666Iter(Serialized<DynIter<'a, u32>, u32, 3>),
+    Packed(PackPixels<DynIter<'a, u8>>),
+    Mono(PackPixels<MonoPixels<'a>>),
+}
+
+impl<'a> DataFormat<'a> {
+    /// Serialize the words into bytes in the order they're sent on a byte oriented bus
+    ///
+    /// 16-bit and 32-bit words are serialized in the byte order of their variant, native byte
+    /// order for `U16` and `U32`. Mutable slices are left untouched.
+    pub fn into_bytes(self) -> Bytes<'a> {
+        Bytes(match self {
+            DataFormat::U8(slice) => Inner::U8(slice.iter().copied()),
+            DataFormat::U16(slice) => Inner::U16(slice.iter().copied().flat_map(u16::to_ne_bytes)),
+            DataFormat::U16BE(slice) => {
+                Inner::U16(slice.iter().copied().flat_map(u16::to_be_bytes))
+            }
+            DataFormat::U16LE(slice) => {
+                Inner::U16(slice.iter().copied().flat_map(u16::to_le_bytes))
+            }
+            DataFormat::U8Iter(iter) => Inner::U8Iter(iter),
+            DataFormat::U16BEIter(iter) => Inner::U16Iter(iter.flat_map(u16::to_be_bytes)),
+            DataFormat::U16LEIter(iter) => Inner::U16Iter(iter.flat_map(u16::to_le_bytes)),
+            DataFormat::U16BERef(slice) => {
+                Inner::U16(slice.iter().copied().flat_map(u16::to_be_bytes))
+            }
+            DataFormat::U16LERef(slice) => {
+                Inner::U16(slice.iter().copied().flat_map(u16::to_le_bytes))
+            }
+            DataFormat::U24(slice) => Inner::U24(slice.iter().copied().flatten()),
+            DataFormat::U24Iter(iter) => Inner::U24Iter(iter.flatten()),
+            DataFormat::RGB666(slice) => {
+                Inner::RGB666(slice.iter().copied().flat_map(rgb666_to_bytes))
+            }
+            DataFormat::RGB666Iter(iter) => Inner::RGB666Iter(iter.flat_map(rgb666_to_bytes)),
+            DataFormat::U32(slice) => Inner::U32(slice.iter().copied().flat_map(u32::to_ne_bytes)),
+            DataFormat::U32BE(slice) => {
+                Inner::U32(slice.iter().copied().flat_map(u32::to_be_bytes))
+            }
+            DataFormat::U32LE(slice) => {
+                Inner::U32(slice.iter().copied().flat_map(u32::to_le_bytes))
+            }
+            DataFormat::U32BEIter(iter) => Inner::U32Iter(iter.flat_map(u32::to_be_bytes)),
+            DataFormat::U32LEIter(iter) => Inner::U32Iter(iter.flat_map(u32::to_le_bytes)),
+            DataFormat::PackedIter(depth, order, iter) => {
+                Inner::Packed(PackPixels::new(iter, depth, order))
+            }
+            DataFormat::MonoIter(order, iter) => {
+                Inner::Mono(PackPixels::new(iter.map(u8::from), BitDepth::One, order))
+            }
+        })
+    }
+}
+
+impl Iterator for Bytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        match &mut self.0 {
+            Inner::U8(iter) => iter.next(),
+            Inner::U8Iter(iter) => iter.next(),
+            Inner::U16(iter) => iter.next(),
+            Inner::U16Iter(iter) => iter.next(),
+            Inner::U24(iter) => iter.next(),
+            Inner::U24Iter(iter) => iter.next(),
+            Inner::U32(iter) => iter.next(),
+            Inner::U32Iter(iter) => iter.next(),
+            Inner::RGB666(iter) => iter.next(),
+            Inner::RGB666Iter(iter) => iter.next(),
+            Inner::Packed(iter) => iter.next(),
+            Inner::Mono(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            Inner::U8(iter) => iter.size_hint(),
+            Inner::U8Iter(iter) => iter.size_hint(),
+            Inner::U16(iter) => iter.size_hint(),
+            Inner::U16Iter(iter) => iter.size_hint(),
+            Inner::U24(iter) => iter.size_hint(),
+            Inner::U24Iter(iter) => iter.size_hint(),
+            Inner::U32(iter) => iter.size_hint(),
+            Inner::U32Iter(iter) => iter.size_hint(),
+            Inner::RGB666(iter) => iter.size_hint(),
+            Inner::RGB666Iter(iter) => iter.size_hint(),
+            Inner::Packed(iter) => iter.size_hint(),
+            Inner::Mono(iter) => iter.size_hint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::packed::BitOrder;
+
+    fn bytes(format: DataFormat<'_>) -> Vec<u8> {
+        format.into_bytes().collect()
+    }
+
+    #[test]
+    fn serializes_words_in_requested_byte_order() {
+        let mut words = [0x0102, 0x0304];
+        assert_eq!(bytes(DataFormat::U16BE(&mut words)), [1, 2, 3, 4]);
+        assert_eq!(bytes(DataFormat::U16LE(&mut words)), [2, 1, 4, 3]);
+        assert_eq!(words, [0x0102, 0x0304]);
+        assert_eq!(bytes(DataFormat::U16LERef(&[0x0506])), [6, 5]);
+        assert_eq!(
+            bytes(DataFormat::U16BEIter(&mut [0x0708u16].iter().copied())),
+            [7, 8]
+        );
+
+        assert_eq!(bytes(DataFormat::U32BE(&mut [0x0102_0304])), [1, 2, 3, 4]);
+        assert_eq!(
+            bytes(DataFormat::U32LEIter(&mut core::iter::once(0x0102_0304))),
+            [4, 3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn serializes_pixels() {
+        assert_eq!(
+            bytes(DataFormat::U24(&[[1, 2, 3], [4, 5, 6]])),
+            [1, 2, 3, 4, 5, 6]
+        );
+        assert_eq!(
+            bytes(DataFormat::RGB666(&[0x3_f000])),
+            rgb666_to_bytes(0x3_f000)
+        );
+        assert_eq!(
+            bytes(DataFormat::MonoIter(
+                BitOrder::MsbFirst,
+                &mut [true, false, true].iter().copied()
+            )),
+            [0xa0]
+        );
+    }
+}
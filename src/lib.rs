@@ -1,5 +1,5 @@
 #![no_std]
-#![feature(type_alias_impl_trait)]
+#![allow(async_fn_in_trait)]
 
 //! A generic display interface
 //!
@@ -8,19 +8,34 @@
 //! to drive a display and allows a driver writer to focus on driving the display itself and only
 //! have to implement a single interface.
 
-use core::future::Future;
+#[cfg(any(feature = "mock", test))]
+extern crate std;
 
+pub mod blocking;
+pub mod bytes;
+#[cfg(any(feature = "mock", test))]
+pub mod mock;
+pub mod packed;
 pub mod prelude;
 
+use core::fmt;
+
+use embedded_hal::{i2c, spi};
+
+use packed::{BitDepth, BitOrder};
+
 /// A ubiquitous error type for all kinds of problems which could happen when communicating with a
 /// display
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum DisplayError {
     /// Invalid data format selected for interface selected
     InvalidFormatError,
     /// Unable to write to bus
-    BusWriteError,
+    BusWriteError(BusErrorKind),
+    /// Unable to read from bus
+    BusReadError(BusErrorKind),
     /// Unable to assert or de-assert data/command switching signal
     DCError,
     /// Unable to assert chip select signal
@@ -31,6 +46,151 @@ pub enum DisplayError {
     RSError,
     /// Attempted to write to a non-existing pixel outside the display's bounds
     OutOfBoundsError,
+    /// The bus address of the display is invalid
+    InvalidAddressError,
+    /// The data doesn't fit into the transfer buffer of the display interface
+    BufferOverflowError,
+}
+
+impl DisplayError {
+    /// Coarse kind of the error, without the details of bus errors
+    pub fn kind(&self) -> DisplayErrorKind {
+        match self {
+            DisplayError::InvalidFormatError => DisplayErrorKind::InvalidFormatError,
+            DisplayError::BusWriteError(_) => DisplayErrorKind::BusWriteError,
+            DisplayError::BusReadError(_) => DisplayErrorKind::BusReadError,
+            DisplayError::DCError => DisplayErrorKind::DCError,
+            DisplayError::CSError => DisplayErrorKind::CSError,
+            DisplayError::DataFormatNotImplemented => DisplayErrorKind::DataFormatNotImplemented,
+            DisplayError::RSError => DisplayErrorKind::RSError,
+            DisplayError::OutOfBoundsError => DisplayErrorKind::OutOfBoundsError,
+            DisplayError::InvalidAddressError => DisplayErrorKind::InvalidAddressError,
+            DisplayError::BufferOverflowError => DisplayErrorKind::BufferOverflowError,
+        }
+    }
+
+    /// Classification of the underlying bus error, `None` if it's not a bus error
+    pub fn bus_error_kind(&self) -> Option<BusErrorKind> {
+        match self {
+            DisplayError::BusWriteError(kind) | DisplayError::BusReadError(kind) => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayError::InvalidFormatError => f.write_str("invalid data format"),
+            DisplayError::BusWriteError(kind) => write!(f, "unable to write to bus: {}", kind),
+            DisplayError::BusReadError(kind) => write!(f, "unable to read from bus: {}", kind),
+            DisplayError::DCError => f.write_str("unable to set data/command signal"),
+            DisplayError::CSError => f.write_str("unable to set chip select signal"),
+            DisplayError::DataFormatNotImplemented => {
+                f.write_str("data format not implemented by the display interface")
+            }
+            DisplayError::RSError => f.write_str("unable to set reset signal"),
+            DisplayError::OutOfBoundsError => f.write_str("pixel outside of the display bounds"),
+            DisplayError::InvalidAddressError => f.write_str("invalid bus address"),
+            DisplayError::BufferOverflowError => f.write_str("transfer buffer too small"),
+        }
+    }
+}
+
+/// Coarse classification of a [`DisplayError`] for drivers which don't care about the details
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum DisplayErrorKind {
+    /// See [`DisplayError::InvalidFormatError`]
+    InvalidFormatError,
+    /// See [`DisplayError::BusWriteError`]
+    BusWriteError,
+    /// See [`DisplayError::BusReadError`]
+    BusReadError,
+    /// See [`DisplayError::DCError`]
+    DCError,
+    /// See [`DisplayError::CSError`]
+    CSError,
+    /// See [`DisplayError::DataFormatNotImplemented`]
+    DataFormatNotImplemented,
+    /// See [`DisplayError::RSError`]
+    RSError,
+    /// See [`DisplayError::OutOfBoundsError`]
+    OutOfBoundsError,
+    /// See [`DisplayError::InvalidAddressError`]
+    InvalidAddressError,
+    /// See [`DisplayError::BufferOverflowError`]
+    BufferOverflowError,
+}
+
+impl From<DisplayError> for DisplayErrorKind {
+    fn from(error: DisplayError) -> Self {
+        error.kind()
+    }
+}
+
+/// Classification of an error reported by the underlying bus, following the error kinds of
+/// `embedded-hal`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum BusErrorKind {
+    /// Bus error, e.g. a misplaced START or STOP condition on I2C
+    Bus,
+    /// The arbitration was lost on a multi-controller bus
+    ArbitrationLoss,
+    /// The display didn't acknowledge its address or the data
+    NoAcknowledge,
+    /// The peripheral receive buffer was overrun
+    Overrun,
+    /// Multiple devices are trying to drive the SPI chip select
+    ModeFault,
+    /// Received data does not conform to the peripheral configuration
+    FrameFormat,
+    /// Unable to assert or de-assert the SPI chip select
+    ChipSelectFault,
+    /// Any other error, or one which can't be classified
+    Other,
+}
+
+impl fmt::Display for BusErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BusErrorKind::Bus => "bus error",
+            BusErrorKind::ArbitrationLoss => "arbitration lost",
+            BusErrorKind::NoAcknowledge => "not acknowledged",
+            BusErrorKind::Overrun => "receive buffer overrun",
+            BusErrorKind::ModeFault => "mode fault",
+            BusErrorKind::FrameFormat => "frame format error",
+            BusErrorKind::ChipSelectFault => "chip select fault",
+            BusErrorKind::Other => "other error",
+        })
+    }
+}
+
+impl From<i2c::ErrorKind> for BusErrorKind {
+    fn from(kind: i2c::ErrorKind) -> Self {
+        match kind {
+            i2c::ErrorKind::Bus => BusErrorKind::Bus,
+            i2c::ErrorKind::ArbitrationLoss => BusErrorKind::ArbitrationLoss,
+            i2c::ErrorKind::NoAcknowledge(_) => BusErrorKind::NoAcknowledge,
+            i2c::ErrorKind::Overrun => BusErrorKind::Overrun,
+            _ => BusErrorKind::Other,
+        }
+    }
+}
+
+impl From<spi::ErrorKind> for BusErrorKind {
+    fn from(kind: spi::ErrorKind) -> Self {
+        match kind {
+            spi::ErrorKind::Overrun => BusErrorKind::Overrun,
+            spi::ErrorKind::ModeFault => BusErrorKind::ModeFault,
+            spi::ErrorKind::FrameFormat => BusErrorKind::FrameFormat,
+            spi::ErrorKind::ChipSelectFault => BusErrorKind::ChipSelectFault,
+            _ => BusErrorKind::Other,
+        }
+    }
 }
 
 /// DI specific data format wrapper around slices of various widths
@@ -52,22 +212,218 @@ pub enum DataFormat<'a> {
     U16BEIter(&'a mut dyn Iterator<Item = u16>),
     /// Iterator over unsigned 16bit values to be sent in little endian byte order
     U16LEIter(&'a mut dyn Iterator<Item = u16>),
+    /// Slice of unsigned 16bit values to be sent in big endian byte order, which can't be swapped
+    /// in place, e.g. because it's stored in flash
+    U16BERef(&'a [u16]),
+    /// Slice of unsigned 16bit values to be sent in little endian byte order, which can't be
+    /// swapped in place, e.g. because it's stored in flash
+    U16LERef(&'a [u16]),
+    /// Slice of 24bit values like RGB888 pixels, each sent as three bytes in the given order
+    U24(&'a [[u8; 3]]),
+    /// Iterator over 24bit values like RGB888 pixels, each sent as three bytes in the given order
+    U24Iter(&'a mut dyn Iterator<Item = [u8; 3]>),
+    /// Slice of RGB666 pixels packed into the lower 18 bits of a `u32`, red in the most
+    /// significant bits, sent as three bytes with the colors in their upper six bits, see
+    /// [`rgb666_to_bytes`]
+    RGB666(&'a [u32]),
+    /// Iterator over RGB666 pixels packed into the lower 18 bits of a `u32`, see
+    /// [`DataFormat::RGB666`]
+    RGB666Iter(&'a mut dyn Iterator<Item = u32>),
+    /// Slice of unsigned 32bit values with the same endianess as the system, not recommended
+    U32(&'a [u32]),
+    /// Slice of unsigned 32bit values to be sent in big endian byte order
+    U32BE(&'a mut [u32]),
+    /// Slice of unsigned 32bit values to be sent in little endian byte order
+    U32LE(&'a mut [u32]),
+    /// Iterator over unsigned 32bit values to be sent in big endian byte order
+    U32BEIter(&'a mut dyn Iterator<Item = u32>),
+    /// Iterator over unsigned 32bit values to be sent in little endian byte order
+    U32LEIter(&'a mut dyn Iterator<Item = u32>),
+    /// Iterator over pixels with the given bit depth, using the lower bits of every value, to be
+    /// packed into bytes in the given bit order, see [`packed`]
+    PackedIter(BitDepth, BitOrder, &'a mut dyn Iterator<Item = u8>),
+    /// Iterator over monochrome pixels to be packed into bytes in the given bit order, see
+    /// [`packed`]
+    MonoIter(BitOrder, &'a mut dyn Iterator<Item = bool>),
+}
+
+/// Serialize a packed RGB666 pixel into the three bytes sent for [`DataFormat::RGB666`], each
+/// color left aligned in its byte with the lower two bits cleared
+pub fn rgb666_to_bytes(pixel: u32) -> [u8; 3] {
+    [
+        (pixel >> 10) as u8 & 0xfc,
+        (pixel >> 4) as u8 & 0xfc,
+        (pixel << 2) as u8 & 0xfc,
+    ]
+}
+
+impl<'a> From<&'a [u8]> for DataFormat<'a> {
+    fn from(slice: &'a [u8]) -> Self {
+        DataFormat::U8(slice)
+    }
+}
+
+/// Sends the words in big endian byte order, as used for RGB565 pixels by MIPI DCS displays
+impl<'a> From<&'a [u16]> for DataFormat<'a> {
+    fn from(slice: &'a [u16]) -> Self {
+        DataFormat::U16BERef(slice)
+    }
+}
+
+impl DataFormat<'_> {
+    /// Name of the variant
+    fn name(&self) -> &'static str {
+        match self {
+            DataFormat::U8(_) => "U8",
+            DataFormat::U16(_) => "U16",
+            DataFormat::U16BE(_) => "U16BE",
+            DataFormat::U16LE(_) => "U16LE",
+            DataFormat::U8Iter(_) => "U8Iter",
+            DataFormat::U16BEIter(_) => "U16BEIter",
+            DataFormat::U16LEIter(_) => "U16LEIter",
+            DataFormat::U16BERef(_) => "U16BERef",
+            DataFormat::U16LERef(_) => "U16LERef",
+            DataFormat::U24(_) => "U24",
+            DataFormat::U24Iter(_) => "U24Iter",
+            DataFormat::RGB666(_) => "RGB666",
+            DataFormat::RGB666Iter(_) => "RGB666Iter",
+            DataFormat::U32(_) => "U32",
+            DataFormat::U32BE(_) => "U32BE",
+            DataFormat::U32LE(_) => "U32LE",
+            DataFormat::U32BEIter(_) => "U32BEIter",
+            DataFormat::U32LEIter(_) => "U32LEIter",
+            DataFormat::PackedIter(..) => "PackedIter",
+            DataFormat::MonoIter(..) => "MonoIter",
+        }
+    }
+
+    /// Number of words in slice variants, `None` for iterators
+    fn len(&self) -> Option<usize> {
+        match self {
+            DataFormat::U8(slice) => Some(slice.len()),
+            DataFormat::U16(slice) | DataFormat::U16BERef(slice) | DataFormat::U16LERef(slice) => {
+                Some(slice.len())
+            }
+            DataFormat::U16BE(slice) | DataFormat::U16LE(slice) => Some(slice.len()),
+            DataFormat::U24(slice) => Some(slice.len()),
+            DataFormat::RGB666(slice) | DataFormat::U32(slice) => Some(slice.len()),
+            DataFormat::U32BE(slice) | DataFormat::U32LE(slice) => Some(slice.len()),
+            DataFormat::U8Iter(_)
+            | DataFormat::U16BEIter(_)
+            | DataFormat::U16LEIter(_)
+            | DataFormat::U24Iter(_)
+            | DataFormat::RGB666Iter(_)
+            | DataFormat::U32BEIter(_)
+            | DataFormat::U32LEIter(_)
+            | DataFormat::PackedIter(..)
+            | DataFormat::MonoIter(..) => None,
+        }
+    }
+}
+
+/// Summarizes the data as variant and number of words, without the contents
+impl fmt::Debug for DataFormat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.len() {
+            Some(len) => write!(f, "{}[{}]", self.name(), len),
+            None => write!(f, "{}[..]", self.name()),
+        }
+    }
+}
+
+/// Summarizes the data as variant and number of words, without the contents
+#[cfg(feature = "defmt")]
+impl defmt::Format for DataFormat<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self.len() {
+            Some(len) => defmt::write!(f, "{=str}[{=usize}]", self.name(), len),
+            None => defmt::write!(f, "{=str}[..]", self.name()),
+        }
+    }
 }
 
 /// This trait implements a write-only interface for a display which has separate data and command
 /// modes. It is the responsibility of implementations to activate the correct mode in their
 /// implementation when corresponding method is called.
 pub trait WriteOnlyDataCommand {
-    type SendCommandsFuture<'a>: Future<Output = Result<(), DisplayError>> + 'a
-    where
-        Self: 'a;
-    type SendDataFuture<'a>: Future<Output = Result<(), DisplayError>> + 'a
-    where
-        Self: 'a;
-
     /// Send a batch of commands to display
-    fn send_commands<'a>(&'a mut self, cmd: DataFormat<'a>) -> Self::SendCommandsFuture<'a>;
+    async fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError>;
 
     /// Send pixel data to display
-    fn send_data<'a>(&'a mut self, buf: DataFormat<'a>) -> Self::SendDataFuture<'a>;
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError>;
+
+    /// Send a batch of commands followed by data, e.g. `RAMWR` and the pixels to write
+    ///
+    /// Implementations sharing the bus with other devices should override this to keep the bus or
+    /// chip select asserted across both phases, so no other device can interleave.
+    async fn send_command_with_data(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: DataFormat<'_>,
+    ) -> Result<(), DisplayError> {
+        self.send_commands(cmd).await?;
+        self.send_data(buf).await
+    }
+
+    /// Send a sequence of command batches each followed by data, e.g. `CASET`, `RASET` and
+    /// `RAMWR` with their parameters, see [`send_command_with_data`](Self::send_command_with_data)
+    async fn send_command_sequence<'a>(
+        &mut self,
+        sequence: impl IntoIterator<Item = (DataFormat<'a>, DataFormat<'a>)>,
+    ) -> Result<(), DisplayError> {
+        for (cmd, buf) in sequence {
+            self.send_command_with_data(cmd, buf).await?;
+        }
+        Ok(())
+    }
+}
+
+/// This trait extends [`WriteOnlyDataCommand`] for displays which allow reading back data, e.g.
+/// identification and status registers or the contents of the display RAM.
+pub trait ReadWriteDataCommand: WriteOnlyDataCommand {
+    /// Read data from display, filling the whole buffer
+    async fn read_data(&mut self, buf: &mut [u8]) -> Result<(), DisplayError>;
+
+    /// Send a batch of commands to display and read the response into the buffer
+    async fn send_command_read(
+        &mut self,
+        cmd: DataFormat<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), DisplayError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::format;
+
+    use super::*;
+
+    #[test]
+    fn formats_errors_and_data() {
+        let error = DisplayError::BusWriteError(BusErrorKind::NoAcknowledge);
+        assert_eq!(
+            format!("{}", error),
+            "unable to write to bus: not acknowledged"
+        );
+        assert_eq!(error.kind(), DisplayErrorKind::BusWriteError);
+
+        let mut words = [0u16; 3];
+        assert_eq!(format!("{:?}", DataFormat::U16BE(&mut words)), "U16BE[3]");
+        assert_eq!(
+            format!("{:?}", DataFormat::U8Iter(&mut (0..4))),
+            "U8Iter[..]"
+        );
+    }
+
+    #[test]
+    fn converts_slices() {
+        static BYTES: [u8; 2] = [1, 2];
+        static WORDS: [u16; 1] = [0x0102];
+
+        assert!(matches!((&BYTES[..]).into(), DataFormat::U8(&[1, 2])));
+        assert!(matches!(
+            (&WORDS[..]).into(),
+            DataFormat::U16BERef(&[0x0102])
+        ));
+    }
 }
@@ -0,0 +1,280 @@
+//! Mock display interface for testing display drivers
+//!
+//! [`MockInterface`] records every call made through [`WriteOnlyDataCommand`] and optionally
+//! checks them against a list of expected [`Transaction`]s, in the spirit of `embedded-hal-mock`.
+//! Errors can be injected at chosen calls to exercise the error handling of a driver.
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+use crate::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+pub mod dcs;
+pub mod snapshot;
+
+/// Owned copy of the words passed in a [`DataFormat`]
+///
+/// Iterator variants are collected into the corresponding slice variant, e.g. `U16BEIter` is
+/// recorded as [`Payload::U16BE`], and so are `U16BERef` slices. 16-bit and 32-bit words are kept
+/// in host order, as passed by the driver. Packed pixels are recorded as the bytes they're packed
+/// into.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Payload {
+    /// Unsigned bytes
+    U8(Vec<u8>),
+    /// Unsigned 16bit values with the same endianess as the system
+    U16(Vec<u16>),
+    /// Unsigned 16bit values to be sent in big endian byte order
+    U16BE(Vec<u16>),
+    /// Unsigned 16bit values to be sent in little endian byte order
+    U16LE(Vec<u16>),
+    /// Unsigned 32bit values with the same endianess as the system
+    U32(Vec<u32>),
+    /// Unsigned 32bit values to be sent in big endian byte order
+    U32BE(Vec<u32>),
+    /// Unsigned 32bit values to be sent in little endian byte order
+    U32LE(Vec<u32>),
+    /// 24bit values sent as three bytes each
+    U24(Vec<[u8; 3]>),
+    /// RGB666 pixels packed into the lower 18 bits
+    RGB666(Vec<u32>),
+}
+
+impl Payload {
+    pub(crate) fn from_format(format: DataFormat<'_>) -> Self {
+        match format {
+            DataFormat::U8(slice) => Payload::U8(slice.to_vec()),
+            DataFormat::U16(slice) => Payload::U16(slice.to_vec()),
+            DataFormat::U16BE(slice) => Payload::U16BE(slice.to_vec()),
+            DataFormat::U16LE(slice) => Payload::U16LE(slice.to_vec()),
+            DataFormat::U8Iter(iter) => Payload::U8(iter.collect()),
+            DataFormat::U16BEIter(iter) => Payload::U16BE(iter.collect()),
+            DataFormat::U16LEIter(iter) => Payload::U16LE(iter.collect()),
+            DataFormat::U16BERef(slice) => Payload::U16BE(slice.to_vec()),
+            DataFormat::U16LERef(slice) => Payload::U16LE(slice.to_vec()),
+            DataFormat::U24(slice) => Payload::U24(slice.to_vec()),
+            DataFormat::U24Iter(iter) => Payload::U24(iter.collect()),
+            DataFormat::RGB666(slice) => Payload::RGB666(slice.to_vec()),
+            DataFormat::RGB666Iter(iter) => Payload::RGB666(iter.collect()),
+            DataFormat::U32(slice) => Payload::U32(slice.to_vec()),
+            DataFormat::U32BE(slice) => Payload::U32BE(slice.to_vec()),
+            DataFormat::U32LE(slice) => Payload::U32LE(slice.to_vec()),
+            DataFormat::U32BEIter(iter) => Payload::U32BE(iter.collect()),
+            DataFormat::U32LEIter(iter) => Payload::U32LE(iter.collect()),
+            format @ (DataFormat::PackedIter(..) | DataFormat::MonoIter(..)) => {
+                Payload::U8(format.into_bytes().collect())
+            }
+        }
+    }
+
+    /// Serialize the words into bytes in the order they're sent on a byte oriented bus
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bytes = |format: DataFormat<'_>| format.into_bytes().collect();
+        match self {
+            Payload::U8(bytes) => bytes.clone(),
+            Payload::U16(words) => bytes(DataFormat::U16(words)),
+            Payload::U16BE(words) => bytes(DataFormat::U16BERef(words)),
+            Payload::U16LE(words) => bytes(DataFormat::U16LERef(words)),
+            Payload::U32(words) => bytes(DataFormat::U32(words)),
+            Payload::U32BE(words) => bytes(DataFormat::U32BEIter(&mut words.iter().copied())),
+            Payload::U32LE(words) => bytes(DataFormat::U32LEIter(&mut words.iter().copied())),
+            Payload::U24(words) => bytes(DataFormat::U24(words)),
+            Payload::RGB666(pixels) => bytes(DataFormat::RGB666(pixels)),
+        }
+    }
+}
+
+/// A single call made on a [`MockInterface`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transaction {
+    /// Call of [`WriteOnlyDataCommand::send_commands`]
+    Commands(Payload),
+    /// Call of [`WriteOnlyDataCommand::send_data`]
+    Data(Payload),
+}
+
+impl Transaction {
+    /// Commands sent as bytes
+    pub fn commands(cmds: &[u8]) -> Self {
+        Transaction::Commands(Payload::U8(cmds.to_vec()))
+    }
+
+    /// Data sent as bytes
+    pub fn data(buf: &[u8]) -> Self {
+        Transaction::Data(Payload::U8(buf.to_vec()))
+    }
+}
+
+/// Display interface recording all calls for later inspection
+#[derive(Debug, Default)]
+pub struct MockInterface {
+    log: Vec<Transaction>,
+    calls: usize,
+    expected: VecDeque<Transaction>,
+    checked: bool,
+    errors: Vec<(usize, DisplayError)>,
+}
+
+impl MockInterface {
+    /// Create new mock interface without any expectations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the call with the given index, counting all calls from zero, fail with `error`
+    ///
+    /// The failing call is still recorded and checked against the expectations.
+    pub fn fail_at(mut self, index: usize, error: DisplayError) -> Self {
+        self.errors.push((index, error));
+        self
+    }
+
+    /// Add transactions which have to be made in the given order
+    ///
+    /// Every call on the interface is compared with the next expected transaction and panics on a
+    /// mismatch or if there are no expectations left. Without any expectations calls are only
+    /// recorded.
+    pub fn expect(&mut self, transactions: &[Transaction]) {
+        self.expected.extend(transactions.iter().cloned());
+        self.checked = true;
+    }
+
+    /// Assert that all expected transactions have been made
+    pub fn done(&mut self) {
+        assert!(
+            self.expected.is_empty(),
+            "not all expected transactions were made, remaining: {:?}",
+            self.expected
+        );
+    }
+
+    /// All calls made on the interface so far
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.log
+    }
+
+    /// Forget the calls recorded so far, expectations and errors as well as the call indices are
+    /// kept
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    fn record(&mut self, transaction: Transaction) -> Result<(), DisplayError> {
+        let index = self.calls;
+        self.calls += 1;
+
+        if self.checked {
+            let expected = self.expected.pop_front();
+            assert_eq!(
+                expected.as_ref(),
+                Some(&transaction),
+                "unexpected transaction #{}",
+                index
+            );
+        }
+        self.log.push(transaction);
+
+        match self.errors.iter().find(|(i, _)| *i == index) {
+            Some((_, error)) => Err(*error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl WriteOnlyDataCommand for MockInterface {
+    async fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.record(Transaction::Commands(Payload::from_format(cmd)))
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.record(Transaction::Data(Payload::from_format(buf)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::block_on;
+    use crate::BusErrorKind;
+
+    #[test]
+    fn records_calls() {
+        let mut iface = MockInterface::new();
+        let mut words = [0x1234, 0x5678];
+
+        block_on(iface.send_commands(DataFormat::U8(&[0x2a, 0x00]))).unwrap();
+        block_on(iface.send_data(DataFormat::U16BE(&mut words))).unwrap();
+        block_on(iface.send_data(DataFormat::U8Iter(&mut (1..4)))).unwrap();
+
+        assert_eq!(
+            iface.transactions(),
+            [
+                Transaction::commands(&[0x2a, 0x00]),
+                Transaction::Data(Payload::U16BE(std::vec![0x1234, 0x5678])),
+                Transaction::data(&[1, 2, 3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sends_command_sequence() {
+        let mut iface = MockInterface::new();
+        iface.expect(&[
+            Transaction::commands(&[0x2a]),
+            Transaction::data(&[0, 0, 0, 1]),
+            Transaction::commands(&[0x2c]),
+            Transaction::data(&[0xff, 0xff]),
+        ]);
+
+        block_on(iface.send_command_sequence([
+            (DataFormat::U8(&[0x2a]), DataFormat::U8(&[0, 0, 0, 1])),
+            (DataFormat::U8(&[0x2c]), DataFormat::U8(&[0xff, 0xff])),
+        ]))
+        .unwrap();
+
+        iface.done();
+    }
+
+    #[test]
+    fn injects_errors() {
+        let mut iface =
+            MockInterface::new().fail_at(1, DisplayError::BusWriteError(BusErrorKind::Other));
+
+        assert!(block_on(iface.send_commands(DataFormat::U8(&[0x2c]))).is_ok());
+        assert!(matches!(
+            block_on(iface.send_data(DataFormat::U8(&[0xff]))),
+            Err(DisplayError::BusWriteError(BusErrorKind::Other))
+        ));
+        assert!(block_on(iface.send_data(DataFormat::U8(&[0xff]))).is_ok());
+        assert_eq!(iface.transactions().len(), 3);
+    }
+
+    #[test]
+    fn meets_expectations() {
+        let mut iface = MockInterface::new();
+        iface.expect(&[Transaction::commands(&[0x29]), Transaction::data(&[0x01])]);
+
+        block_on(iface.send_commands(DataFormat::U8(&[0x29]))).unwrap();
+        block_on(iface.send_data(DataFormat::U8(&[0x01]))).unwrap();
+
+        iface.done();
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected transaction #0")]
+    fn panics_on_unexpected_call() {
+        let mut iface = MockInterface::new();
+        iface.expect(&[Transaction::commands(&[0x29])]);
+
+        block_on(iface.send_commands(DataFormat::U8(&[0x28]))).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "not all expected transactions were made")]
+    fn panics_on_missing_call() {
+        let mut iface = MockInterface::new();
+        iface.expect(&[Transaction::commands(&[0x29])]);
+
+        iface.done();
+    }
+}
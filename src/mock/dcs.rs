@@ -0,0 +1,313 @@
+//! Virtual MIPI DCS display
+//!
+//! [`DcsDisplay`] interprets the MIPI Display Command Set commands sent by drivers for controllers
+//! like the ST7789 or ILI9341 and renders the pixel data into an in-memory framebuffer, allowing
+//! to compare the output of a driver with golden images on the host.
+//!
+//! The following commands are understood, all others and their parameters are ignored:
+//!
+//! - `CASET` (0x2A) and `RASET` (0x2B) to set the address window
+//! - `RAMWR` (0x2C) and `RAMWRC` (0x3C) to write pixels into the window
+//! - `MADCTL` (0x36) for row/column order and exchange (`MY`, `MX`, `MV`) and BGR order
+//! - `COLMOD` (0x3A) to select 16-bit RGB565, 18-bit RGB666 or 24-bit RGB888 pixels
+//!
+//! Every byte passed to [`send_commands`] starts a new command, parameters and pixel data are only
+//! taken from [`send_data`] like the D/C line of the real hardware does.
+//!
+//! [`send_commands`]: WriteOnlyDataCommand::send_commands
+//! [`send_data`]: WriteOnlyDataCommand::send_data
+
+use std::vec;
+use std::vec::Vec;
+
+use super::Payload;
+use crate::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+/// Column address set
+pub const CASET: u8 = 0x2A;
+/// Row address set
+pub const RASET: u8 = 0x2B;
+/// Memory write
+pub const RAMWR: u8 = 0x2C;
+/// Memory write continue
+pub const RAMWRC: u8 = 0x3C;
+/// Memory data access control
+pub const MADCTL: u8 = 0x36;
+/// Interface pixel format
+pub const COLMOD: u8 = 0x3A;
+
+const MADCTL_MY: u8 = 0x80;
+const MADCTL_MX: u8 = 0x40;
+const MADCTL_MV: u8 = 0x20;
+const MADCTL_BGR: u8 = 0x08;
+
+/// Pixel format selected with `COLMOD`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PixelFormat {
+    Rgb565,
+    Rgb666,
+    Rgb888,
+}
+
+impl PixelFormat {
+    fn from_colmod(colmod: u8) -> Option<Self> {
+        // The lower nibble selects the format of the control interface
+        match colmod & 0x07 {
+            0x05 => Some(PixelFormat::Rgb565),
+            0x06 => Some(PixelFormat::Rgb666),
+            0x07 => Some(PixelFormat::Rgb888),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Rgb666 | PixelFormat::Rgb888 => 3,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> [u8; 3] {
+        match self {
+            PixelFormat::Rgb565 => {
+                let v = u16::from_be_bytes([bytes[0], bytes[1]]);
+                let r = (v >> 11) as u8 & 0x1f;
+                let g = (v >> 5) as u8 & 0x3f;
+                let b = v as u8 & 0x1f;
+                [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+            }
+            PixelFormat::Rgb666 => {
+                let expand = |c: u8| c & 0xfc | c >> 6;
+                [expand(bytes[0]), expand(bytes[1]), expand(bytes[2])]
+            }
+            PixelFormat::Rgb888 => [bytes[0], bytes[1], bytes[2]],
+        }
+    }
+}
+
+/// Virtual display decoding MIPI DCS commands into an RGB888 framebuffer
+#[derive(Debug)]
+pub struct DcsDisplay {
+    width: u16,
+    height: u16,
+    pixels: Vec<[u8; 3]>,
+    command: Option<u8>,
+    params: Vec<u8>,
+    columns: (u16, u16),
+    rows: (u16, u16),
+    cursor: (u16, u16),
+    madctl: u8,
+    format: PixelFormat,
+    partial: Vec<u8>,
+}
+
+impl DcsDisplay {
+    /// Create new virtual display with the given size of the frame memory, filled with black
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 3]; usize::from(width) * usize::from(height)],
+            command: None,
+            params: Vec::new(),
+            columns: (0, width.saturating_sub(1)),
+            rows: (0, height.saturating_sub(1)),
+            cursor: (0, 0),
+            madctl: 0,
+            format: PixelFormat::Rgb666,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Width of the frame memory
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Height of the frame memory
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Color of the pixel at the given position as RGB888, `None` if it's outside the display
+    pub fn pixel(&self, x: u16, y: u16) -> Option<[u8; 3]> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[usize::from(y) * usize::from(self.width) + usize::from(x)])
+        } else {
+            None
+        }
+    }
+
+    /// All pixels as RGB888 in row-major order
+    pub fn pixels(&self) -> &[[u8; 3]] {
+        &self.pixels
+    }
+
+    fn start_command(&mut self, command: u8) {
+        self.command = Some(command);
+        self.params.clear();
+        self.partial.clear();
+
+        if command == RAMWR {
+            self.cursor = (self.columns.0, self.rows.0);
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        match self.command {
+            Some(RAMWR) | Some(RAMWRC) => {
+                self.partial.push(byte);
+                if self.partial.len() == self.format.bytes_per_pixel() {
+                    let color = self.format.decode(&self.partial);
+                    self.partial.clear();
+                    self.write_pixel(color);
+                }
+            }
+            Some(CASET) | Some(RASET) | Some(MADCTL) | Some(COLMOD) => {
+                self.params.push(byte);
+                self.apply_params();
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_params(&mut self) {
+        let window = |p: &[u8]| {
+            (
+                u16::from_be_bytes([p[0], p[1]]),
+                u16::from_be_bytes([p[2], p[3]]),
+            )
+        };
+
+        match (self.command, self.params.len()) {
+            (Some(CASET), 4) => self.columns = window(&self.params),
+            (Some(RASET), 4) => self.rows = window(&self.params),
+            (Some(MADCTL), 1) => self.madctl = self.params[0],
+            (Some(COLMOD), 1) => {
+                if let Some(format) = PixelFormat::from_colmod(self.params[0]) {
+                    self.format = format;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write_pixel(&mut self, color: [u8; 3]) {
+        let (column, row) = self.cursor;
+
+        // Map the logical address to the frame memory according to the memory access control
+        let (mut x, mut y) = if self.madctl & MADCTL_MV != 0 {
+            (row, column)
+        } else {
+            (column, row)
+        };
+        if self.madctl & MADCTL_MX != 0 {
+            x = self.width.wrapping_sub(1).wrapping_sub(x);
+        }
+        if self.madctl & MADCTL_MY != 0 {
+            y = self.height.wrapping_sub(1).wrapping_sub(y);
+        }
+
+        let color = if self.madctl & MADCTL_BGR != 0 {
+            [color[2], color[1], color[0]]
+        } else {
+            color
+        };
+
+        // Writes outside of the frame memory are dropped like on the real hardware
+        if x < self.width && y < self.height {
+            self.pixels[usize::from(y) * usize::from(self.width) + usize::from(x)] = color;
+        }
+
+        // Advance through the window, wrapping around at its end
+        self.cursor = if column < self.columns.1 {
+            (column + 1, row)
+        } else if row < self.rows.1 {
+            (self.columns.0, row + 1)
+        } else {
+            (self.columns.0, self.rows.0)
+        };
+    }
+}
+
+impl WriteOnlyDataCommand for DcsDisplay {
+    async fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        for command in Payload::from_format(cmd).to_bytes() {
+            self.start_command(command);
+        }
+
+        Ok(())
+    }
+
+    async fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        for byte in Payload::from_format(buf).to_bytes() {
+            self.write_byte(byte);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::block_on;
+
+    fn send(display: &mut DcsDisplay, cmd: u8, params: &[u8]) {
+        block_on(display.send_commands(DataFormat::U8(&[cmd]))).unwrap();
+        block_on(display.send_data(DataFormat::U8(params))).unwrap();
+    }
+
+    #[test]
+    fn writes_rgb565_into_window() {
+        let mut display = DcsDisplay::new(4, 4);
+        let mut pixels = [0xf800, 0x07e0, 0x001f, 0xffff];
+
+        send(&mut display, COLMOD, &[0x55]);
+        send(&mut display, CASET, &[0, 1, 0, 2]);
+        send(&mut display, RASET, &[0, 2, 0, 3]);
+        block_on(display.send_commands(DataFormat::U8(&[RAMWR]))).unwrap();
+        block_on(display.send_data(DataFormat::U16BE(&mut pixels))).unwrap();
+
+        assert_eq!(display.pixel(1, 2), Some([0xff, 0, 0]));
+        assert_eq!(display.pixel(2, 2), Some([0, 0xff, 0]));
+        assert_eq!(display.pixel(1, 3), Some([0, 0, 0xff]));
+        assert_eq!(display.pixel(2, 3), Some([0xff, 0xff, 0xff]));
+        assert_eq!(display.pixel(0, 0), Some([0, 0, 0]));
+    }
+
+    #[test]
+    fn parameters_in_command_batch() {
+        let mut display = DcsDisplay::new(4, 4);
+
+        // The bytes after the first are commands too and must not be taken as parameters
+        block_on(display.send_commands(DataFormat::U8(&[CASET, 0, 3, 0, 3]))).unwrap();
+        send(&mut display, RASET, &[0, 0, 0, 0]);
+        send(&mut display, RAMWR, &[0xfc, 0, 0]);
+
+        assert_eq!(display.columns, (0, 3));
+        assert_eq!(display.pixel(0, 0), Some([0xff, 0, 0]));
+
+        // Only the last command of a batch receives the following data
+        block_on(display.send_commands(DataFormat::U8(&[COLMOD, CASET]))).unwrap();
+        block_on(display.send_data(DataFormat::U8(&[0, 2, 0, 3]))).unwrap();
+        send(&mut display, RAMWR, &[0, 0xfc, 0]);
+
+        assert_eq!(display.format, PixelFormat::Rgb666);
+        assert_eq!(display.pixel(2, 0), Some([0, 0xff, 0]));
+    }
+
+    #[test]
+    fn madctl_exchanges_and_mirrors() {
+        let mut display = DcsDisplay::new(4, 2);
+
+        send(&mut display, COLMOD, &[0x66]);
+        send(&mut display, MADCTL, &[MADCTL_MV | MADCTL_MX | MADCTL_BGR]);
+        send(&mut display, CASET, &[0, 1, 0, 1]);
+        send(&mut display, RASET, &[0, 0, 0, 0]);
+        send(&mut display, RAMWR, &[0xfc, 0, 0]);
+
+        // Column 1 maps to row 1, row 0 maps to the last column after mirroring
+        assert_eq!(display.pixel(3, 1), Some([0, 0, 0xff]));
+    }
+}
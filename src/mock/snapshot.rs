@@ -0,0 +1,431 @@
+//! Image snapshots of pixel data sent to a display
+//!
+//! [`Snapshot`] captures the pixel stream a driver passes to
+//! [`send_data`](crate::WriteOnlyDataCommand::send_data) as an RGB888 image, which can be written
+//! to PPM or PNG files and compared with golden images, e.g. in CI.
+//!
+//! Reading and writing PNG files requires the `snapshot` feature.
+
+#[cfg(any(feature = "snapshot", test))]
+use core::convert::TryFrom;
+use std::fmt;
+#[cfg(any(feature = "snapshot", test))]
+use std::io::Read;
+use std::io::{self, Write};
+use std::vec;
+use std::vec::Vec;
+
+use super::dcs::DcsDisplay;
+use super::Payload;
+use crate::{DataFormat, DisplayError};
+
+/// Layout of the pixels in a captured [`DataFormat`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 16-bit RGB565 pixels, either as words or as big endian byte pairs in `U8`
+    Rgb565,
+    /// 24-bit RGB888 pixels as three bytes in red, green, blue order
+    Rgb888,
+    /// 1 bit per pixel in row-major order, most significant bit first, every row starting at a new
+    /// byte
+    Mono,
+    /// 1 bit per pixel with every byte covering 8 vertical pixels, least significant bit at the top,
+    /// like the pages of the SSD1306
+    MonoPages,
+}
+
+/// Differences found by [`Snapshot::compare`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difference {
+    /// The snapshots have different sizes, as `(width, height)`
+    Size {
+        /// Size of the snapshot `compare` was called on
+        expected: (usize, usize),
+        /// Size of the other snapshot
+        actual: (usize, usize),
+    },
+    /// Some pixels differ
+    Pixels {
+        /// Position of the first differing pixel in row-major order
+        first: (usize, usize),
+        /// Top left corner of the region containing all differing pixels
+        top_left: (usize, usize),
+        /// Bottom right corner of the region containing all differing pixels, inclusive
+        bottom_right: (usize, usize),
+        /// Number of differing pixels
+        count: usize,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Size { expected, actual } => write!(
+                f,
+                "size differs: expected {}x{}, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            Difference::Pixels {
+                first,
+                top_left,
+                bottom_right,
+                count,
+            } => write!(
+                f,
+                "{} pixels differ in ({}, {})..=({}, {}), first at ({}, {})",
+                count, top_left.0, top_left.1, bottom_right.0, bottom_right.1, first.0, first.1
+            ),
+        }
+    }
+}
+
+/// RGB888 image of the pixels sent to a display
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Snapshot {
+    /// Create new snapshot of the given size, filled with black
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 3]; width * height],
+        }
+    }
+
+    /// Capture the pixels in `data` into a new snapshot of the given size
+    ///
+    /// Pixels are filled in row-major order, or column by column within each page for
+    /// [`PixelFormat::MonoPages`]; pixels not covered by `data` are left black. Returns
+    /// `OutOfBoundsError` if `data` doesn't fit into the image and `InvalidFormatError` if it ends
+    /// in the middle of a pixel.
+    pub fn capture(
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        data: DataFormat<'_>,
+    ) -> Result<Self, DisplayError> {
+        let mut snapshot = Self::new(width, height);
+        let payload = Payload::from_format(data);
+
+        match format {
+            PixelFormat::Rgb565 => {
+                let words = match payload {
+                    Payload::U16(words) | Payload::U16BE(words) | Payload::U16LE(words) => words,
+                    Payload::U8(bytes) => {
+                        if bytes.len() % 2 != 0 {
+                            return Err(DisplayError::InvalidFormatError);
+                        }
+                        bytes
+                            .chunks(2)
+                            .map(|p| u16::from_be_bytes([p[0], p[1]]))
+                            .collect()
+                    }
+                    _ => return Err(DisplayError::InvalidFormatError),
+                };
+                snapshot.fill(words.into_iter().map(rgb565))?;
+            }
+            PixelFormat::Rgb888 => {
+                let bytes = payload.to_bytes();
+                if bytes.len() % 3 != 0 {
+                    return Err(DisplayError::InvalidFormatError);
+                }
+                snapshot.fill(bytes.chunks(3).map(|p| [p[0], p[1], p[2]]))?;
+            }
+            PixelFormat::Mono => {
+                let bytes = payload.to_bytes();
+                let stride = width.div_ceil(8);
+                if bytes.len() > stride * height {
+                    return Err(DisplayError::OutOfBoundsError);
+                }
+                for (i, byte) in bytes.into_iter().enumerate() {
+                    let y = i / stride;
+                    for bit in 0..8 {
+                        let x = i % stride * 8 + bit;
+                        if x < width {
+                            snapshot.pixels[y * width + x] = mono(byte & 0x80 >> bit != 0);
+                        }
+                    }
+                }
+            }
+            PixelFormat::MonoPages => {
+                let bytes = payload.to_bytes();
+                let pages = height.div_ceil(8);
+                if bytes.len() > width * pages {
+                    return Err(DisplayError::OutOfBoundsError);
+                }
+                for (i, byte) in bytes.into_iter().enumerate() {
+                    let x = i % width;
+                    for bit in 0..8 {
+                        let y = i / width * 8 + bit;
+                        if y < height {
+                            snapshot.pixels[y * width + x] = mono(byte & 1 << bit != 0);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Width of the image
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Color of the pixel at the given position as RGB888, `None` if it's outside the image
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// All pixels as RGB888 in row-major order
+    pub fn pixels(&self) -> &[[u8; 3]] {
+        &self.pixels
+    }
+
+    /// Compare with another snapshot, `None` if both are identical
+    pub fn compare(&self, other: &Snapshot) -> Option<Difference> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Some(Difference::Size {
+                expected: (self.width, self.height),
+                actual: (other.width, other.height),
+            });
+        }
+
+        let mut positions = self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, _)| (i % self.width, i / self.width));
+
+        let first = positions.next()?;
+        let (mut top_left, mut bottom_right, mut count) = (first, first, 1);
+        for (x, y) in positions {
+            top_left = (top_left.0.min(x), top_left.1.min(y));
+            bottom_right = (bottom_right.0.max(x), bottom_right.1.max(y));
+            count += 1;
+        }
+
+        Some(Difference::Pixels {
+            first,
+            top_left,
+            bottom_right,
+            count,
+        })
+    }
+
+    /// Write the image as binary PPM (P6)
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.pixels.concat())
+    }
+
+    /// Write the image as 8-bit RGB PNG
+    #[cfg(any(feature = "snapshot", test))]
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let (width, height) = self.png_size()?;
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()?
+            .write_image_data(&self.pixels.concat())?;
+        Ok(())
+    }
+
+    /// Read a PNG image, e.g. a golden image written with [`write_png`](Self::write_png)
+    ///
+    /// Grayscale and palette images are converted to RGB888, alpha is dropped.
+    #[cfg(any(feature = "snapshot", test))]
+    pub fn read_png<R: Read>(reader: R) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+
+        let channels = info.color_type.samples();
+        let mut snapshot = Self::new(info.width as usize, info.height as usize);
+        for (y, row) in buf.chunks(info.line_size).take(snapshot.height).enumerate() {
+            for (x, p) in row.chunks(channels).take(snapshot.width).enumerate() {
+                snapshot.pixels[y * snapshot.width + x] = match info.color_type {
+                    png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => [p[0]; 3],
+                    _ => [p[0], p[1], p[2]],
+                };
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    fn fill(&mut self, pixels: impl Iterator<Item = [u8; 3]>) -> Result<(), DisplayError> {
+        for (i, color) in pixels.enumerate() {
+            *self
+                .pixels
+                .get_mut(i)
+                .ok_or(DisplayError::OutOfBoundsError)? = color;
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "snapshot", test))]
+    fn png_size(&self) -> io::Result<(u32, u32)> {
+        match (u32::try_from(self.width), u32::try_from(self.height)) {
+            (Ok(width), Ok(height)) => Ok((width, height)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "image too large for PNG",
+            )),
+        }
+    }
+}
+
+impl From<&DcsDisplay> for Snapshot {
+    fn from(display: &DcsDisplay) -> Self {
+        Self {
+            width: usize::from(display.width()),
+            height: usize::from(display.height()),
+            pixels: display.pixels().to_vec(),
+        }
+    }
+}
+
+fn rgb565(v: u16) -> [u8; 3] {
+    let r = (v >> 11) as u8 & 0x1f;
+    let g = (v >> 5) as u8 & 0x3f;
+    let b = v as u8 & 0x1f;
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+fn mono(on: bool) -> [u8; 3] {
+    if on {
+        [0xff; 3]
+    } else {
+        [0; 3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_formats() {
+        let mut words = [0xf800, 0x07e0, 0x001f, 0xffff];
+        let rgb565 = Snapshot::capture(2, 2, PixelFormat::Rgb565, DataFormat::U16LE(&mut words));
+        let rgb565 = rgb565.unwrap();
+        assert_eq!(rgb565.pixel(0, 0), Some([0xff, 0, 0]));
+        assert_eq!(rgb565.pixel(1, 0), Some([0, 0xff, 0]));
+        assert_eq!(rgb565.pixel(0, 1), Some([0, 0, 0xff]));
+
+        let bytes = Snapshot::capture(2, 1, PixelFormat::Rgb565, DataFormat::U8(&[0xf8, 0x00]));
+        assert_eq!(bytes.unwrap().pixel(0, 0), Some([0xff, 0, 0]));
+
+        let rgb888 = Snapshot::capture(2, 1, PixelFormat::Rgb888, DataFormat::U8(&[1, 2, 3]));
+        assert_eq!(rgb888.unwrap().pixels(), [[1, 2, 3], [0, 0, 0]]);
+
+        let mono = Snapshot::capture(
+            10,
+            2,
+            PixelFormat::Mono,
+            DataFormat::U8(&[0x80, 0x40, 0x01, 0]),
+        );
+        let mono = mono.unwrap();
+        assert_eq!(mono.pixel(0, 0), Some([0xff; 3]));
+        assert_eq!(mono.pixel(9, 0), Some([0xff; 3]));
+        assert_eq!(mono.pixel(7, 1), Some([0xff; 3]));
+        assert_eq!(mono.pixel(1, 0), Some([0; 3]));
+
+        let pages = Snapshot::capture(
+            2,
+            16,
+            PixelFormat::MonoPages,
+            DataFormat::U8(&[1, 0, 0, 0x80]),
+        );
+        let pages = pages.unwrap();
+        assert_eq!(pages.pixel(0, 0), Some([0xff; 3]));
+        assert_eq!(pages.pixel(1, 15), Some([0xff; 3]));
+        assert_eq!(pages.pixel(0, 8), Some([0; 3]));
+    }
+
+    #[test]
+    fn rejects_too_much_data() {
+        assert!(matches!(
+            Snapshot::capture(1, 1, PixelFormat::Rgb888, DataFormat::U8(&[0; 6])),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert!(matches!(
+            Snapshot::capture(8, 1, PixelFormat::Mono, DataFormat::U8(&[0; 2])),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert!(matches!(
+            Snapshot::capture(1, 1, PixelFormat::Rgb565, DataFormat::U8(&[0])),
+            Err(DisplayError::InvalidFormatError)
+        ));
+    }
+
+    #[test]
+    fn ppm_and_png_round_trip() {
+        let snapshot = Snapshot::capture(
+            2,
+            1,
+            PixelFormat::Rgb888,
+            DataFormat::U8(&[1, 2, 3, 4, 5, 6]),
+        )
+        .unwrap();
+
+        let mut ppm = Vec::new();
+        snapshot.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06");
+
+        let mut png = Vec::new();
+        snapshot.write_png(&mut png).unwrap();
+        assert_eq!(Snapshot::read_png(&png[..]).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn reports_differing_region() {
+        let expected = Snapshot::new(4, 4);
+        let mut actual = Snapshot::new(4, 4);
+        assert_eq!(expected.compare(&actual), None);
+
+        actual.pixels[2 * 4 + 3] = [1, 0, 0];
+        actual.pixels[3 * 4 + 1] = [0, 1, 0];
+        let difference = expected.compare(&actual).unwrap();
+        assert_eq!(
+            difference,
+            Difference::Pixels {
+                first: (3, 2),
+                top_left: (1, 2),
+                bottom_right: (3, 3),
+                count: 2,
+            }
+        );
+        assert_eq!(
+            std::format!("{}", difference),
+            "2 pixels differ in (1, 2)..=(3, 3), first at (3, 2)"
+        );
+
+        assert!(matches!(
+            expected.compare(&Snapshot::new(4, 3)),
+            Some(Difference::Size { .. })
+        ));
+    }
+}
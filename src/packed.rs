@@ -0,0 +1,130 @@
+//! Packing of sub-byte pixels
+//!
+//! Monochrome and grayscale displays take several pixels per byte. [`DataFormat::PackedIter`]
+//! and [`DataFormat::MonoIter`] let drivers stream one value per pixel, which display interface
+//! implementations pack into bytes on the fly with [`PackPixels`].
+//!
+//! [`DataFormat::PackedIter`]: crate::DataFormat::PackedIter
+//! [`DataFormat::MonoIter`]: crate::DataFormat::MonoIter
+
+/// Number of bits per packed pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BitDepth {
+    /// 1 bit per pixel, 8 pixels per byte
+    One,
+    /// 2 bits per pixel, 4 pixels per byte
+    Two,
+    /// 4 bits per pixel, 2 pixels per byte
+    Four,
+}
+
+impl BitDepth {
+    /// Number of bits per pixel
+    pub fn bits(self) -> u8 {
+        match self {
+            BitDepth::One => 1,
+            BitDepth::Two => 2,
+            BitDepth::Four => 4,
+        }
+    }
+}
+
+/// Order in which packed pixels fill a byte
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BitOrder {
+    /// The first pixel is stored in the most significant bits
+    MsbFirst,
+    /// The first pixel is stored in the least significant bits
+    LsbFirst,
+}
+
+/// Iterator packing pixels into bytes
+///
+/// Only the lower bits of every pixel value are used, a trailing incomplete byte is padded with
+/// zero bits.
+#[derive(Clone, Debug)]
+pub struct PackPixels<I> {
+    pixels: I,
+    depth: BitDepth,
+    order: BitOrder,
+}
+
+impl<I> PackPixels<I>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Pack the pixels with the given depth and order
+    pub fn new(pixels: I, depth: BitDepth, order: BitOrder) -> Self {
+        Self {
+            pixels,
+            depth,
+            order,
+        }
+    }
+}
+
+impl<I> Iterator for PackPixels<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let bits = self.depth.bits();
+        let mask = (1 << bits) - 1;
+
+        let mut byte = 0;
+        for i in 0..8 / bits {
+            let pixel = match self.pixels.next() {
+                Some(pixel) => pixel & mask,
+                None if i == 0 => return None,
+                None => break,
+            };
+
+            let shift = match self.order {
+                BitOrder::MsbFirst => 8 - bits * (i + 1),
+                BitOrder::LsbFirst => bits * i,
+            };
+            byte |= pixel << shift;
+        }
+
+        Some(byte)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let per_byte = usize::from(8 / self.depth.bits());
+        let (lower, upper) = self.pixels.size_hint();
+        (
+            lower.div_ceil(per_byte),
+            upper.map(|upper| upper.div_ceil(per_byte)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn packs_pixels() {
+        let pack = |pixels: &[u8], depth, order| {
+            PackPixels::new(pixels.iter().copied(), depth, order).collect::<Vec<_>>()
+        };
+
+        let mono = [1, 0, 0, 0, 0, 0, 1, 1, 1];
+        assert_eq!(pack(&mono, BitDepth::One, BitOrder::MsbFirst), [0x83, 0x80]);
+        assert_eq!(pack(&mono, BitDepth::One, BitOrder::LsbFirst), [0xc1, 0x01]);
+
+        let gray = [3, 0, 1, 2, 0xff];
+        assert_eq!(pack(&gray, BitDepth::Two, BitOrder::MsbFirst), [0xc6, 0xc0]);
+        assert_eq!(
+            pack(&gray, BitDepth::Four, BitOrder::LsbFirst),
+            [0x03, 0x21, 0x0f]
+        );
+        assert!(pack(&[], BitDepth::Four, BitOrder::MsbFirst).is_empty());
+    }
+}
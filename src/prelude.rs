@@ -1,2 +1,3 @@
 pub use crate::DisplayError as _display_interface_DisplayError;
+pub use crate::ReadWriteDataCommand as _display_interface_ReadWriteDataCommand;
 pub use crate::WriteOnlyDataCommand as _display_interface_WriteOnlyDataCommand;